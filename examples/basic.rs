@@ -16,8 +16,8 @@ fn main() -> Result<(), MrXlsxError> {
         ])?;
         for i in 1..1_000 {
             sheet.write_row(&[
-                CellValue::text(&format!("A{i}")),
-                CellValue::text(&format!("{}", i)),
+                CellValue::text(format!("A{i}")),
+                CellValue::text(format!("{}", i)),
                 CellValue::text("Pass"),
             ])?;
            
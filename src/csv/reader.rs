@@ -48,6 +48,11 @@ impl XlsxReader {
             .into_iter()
             .filter_map(|(rid, name)| rid_to_path.get(&rid).map(|p| (name, p.clone())))
             .collect();
+        // chartsheets have no cells to read
+        let sheet_order = sheet_order
+            .into_iter()
+            .filter(|name| sheet_paths.contains_key(name))
+            .collect();
 
         let shared_strings = Arc::new(parse_shared_strings(&mut archive)?);
 
@@ -189,6 +194,8 @@ impl Iterator for RowIter {
         let slice = &self.xml[self.offset..];
         let mut xml = XmlReader::from_reader(slice);
         xml.config_mut().trim_text(true);
+        // parsing resumes after the last row, so the enclosing start tags are not seen
+        xml.config_mut().allow_unmatched_ends = true;
 
         loop {
             self.buf.clear();
@@ -243,10 +250,8 @@ impl Iterator for RowIter {
                     _ => {}
                 },
 
-                Ok(Event::Text(ref e)) => {
-                    if self.state.in_v || self.state.in_t {
-                        self.state.value_buf.push_str(&text_val(e));
-                    }
+                Ok(Event::Text(ref e)) if self.state.in_v || self.state.in_t => {
+                    self.state.value_buf.push_str(&text_val(e));
                 }
 
                 Ok(Event::End(ref e)) => match e.name().as_ref() {
@@ -9,7 +9,11 @@ pub enum MrXlsxError {
     AlreadyExists(String),
     NotFound(String),
     Io(String),
-    ZipError(String)
+    ZipError(String),
+    InvalidRange(String),
+    OverlappingMerge(String),
+    OutOfOrder(String),
+    InvalidArgument(String),
 }
 
 impl From<std::io::Error> for MrXlsxError {
@@ -31,7 +35,11 @@ impl fmt::Display for MrXlsxError {
             MrXlsxError::Io(e) => write!(f, "IO error: {e}"),
 			MrXlsxError::AlreadyExists(e) => write!(f, "Already Exists Sheet : {e}"),
 			MrXlsxError::NotFound(e) => write!(f, "Mr Xlsx Not Found : {e}"),
-			MrXlsxError::ZipError(e) => write!(f, "Zip Error : {e}")
+			MrXlsxError::ZipError(e) => write!(f, "Zip Error : {e}"),
+			MrXlsxError::InvalidRange(e) => write!(f, "Invalid Range : {e}"),
+			MrXlsxError::OverlappingMerge(e) => write!(f, "Overlapping Merge : {e}"),
+			MrXlsxError::OutOfOrder(e) => write!(f, "Out Of Order Write : {e}"),
+			MrXlsxError::InvalidArgument(e) => write!(f, "Invalid Argument : {e}"),
         }
     }
 }
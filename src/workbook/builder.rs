@@ -1,8 +1,15 @@
-use crate::{Result, workbook::Workbook};
+use crate::{
+    Result,
+    workbook::{
+        Workbook, WorkbookOptions, cell::NonFinitePolicy, formula::CalcOptions,
+        properties::DocProperties, shared_strings::DEFAULT_SHARED_STRINGS_CAPACITY,
+    },
+};
 
 pub struct WorkbookBuilder<T: Into<String>> {
-    path: T,
+    path: Option<T>,
     sheets: Vec<T>,
+    options: WorkbookOptions,
 }
 
 impl<T> WorkbookBuilder<T>
@@ -11,8 +18,9 @@ where
 {
     pub fn new(path: T) -> Self {
         Self {
-            path,
+            path: Some(path),
             sheets: vec![],
+            options: WorkbookOptions::default(),
         }
     }
 
@@ -21,10 +29,60 @@ where
         self
     }
 
+    /// Write text cells through `xl/sharedStrings.xml` instead of inline strings.
+    pub fn shared_strings(mut self, enabled: bool) -> Self {
+        self.options.shared_strings = enabled.then_some(DEFAULT_SHARED_STRINGS_CAPACITY);
+        self
+    }
+
+    /// Enable shared strings, deduplicating at most `capacity` distinct strings in memory.
+    /// Strings seen after the table is full are still shared, just not deduplicated.
+    pub fn shared_strings_capacity(mut self, capacity: usize) -> Self {
+        self.options.shared_strings = Some(capacity);
+        self
+    }
+
+    /// Store dates as days since 1904-01-01 instead of 1900-01-01, as older Mac
+    /// versions of Excel did.
+    pub fn date_1904(mut self, enabled: bool) -> Self {
+        self.options.date_1904 = enabled;
+        self
+    }
+
+    /// How NaN and infinite numbers are written, `#NUM!` error cells by default.
+    pub fn non_finite(mut self, policy: NonFinitePolicy) -> Self {
+        self.options.non_finite = policy;
+        self
+    }
+
+    /// Calculation mode and recalculation settings, see [`CalcOptions`].
+    pub fn calc_options(mut self, calc: CalcOptions) -> Self {
+        self.options.calc = calc;
+        self
+    }
+
+    /// Title, author, custom properties and other metadata of the document.
+    pub fn properties(mut self, properties: DocProperties) -> Self {
+        self.options.properties = properties;
+        self
+    }
+
     pub fn build(self) -> Result<Workbook> {
-        Ok(Workbook::new_with_builder(
-            self.path.into(),
+        Workbook::new_with_builder(
+            self.path.map(Into::into),
             self.sheets.into_iter().map(|s| s.into()).collect(),
-        )?)
+            self.options,
+        )
+    }
+}
+
+impl WorkbookBuilder<String> {
+    /// Workbook without an output path, finished with `finish_into` or `finish_to_vec`.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            sheets: vec![],
+            options: WorkbookOptions::default(),
+        }
     }
 }
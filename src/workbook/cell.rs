@@ -1,8 +1,33 @@
+use std::time::Duration;
+
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{
+        date::{Date, DateTime, Time},
+        formula::{Formula, FormulaResult},
+        hyperlink::Hyperlink,
+        rich_text::TextRun,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum CellValue {
     Number(f64),
     Text(String),
+    /// Text made of runs with their own fonts.
+    RichText(Vec<TextRun>),
     Bool(bool),
-    Formula(String),
+    Formula(Formula),
+    Hyperlink(Hyperlink),
+    /// Dates and times are stored as serial numbers and shown with a default
+    /// date or time format unless the cell style sets one.
+    Date(Date),
+    Time(Time),
+    DateTime(DateTime),
+    /// Elapsed time, e.g. `[h]:mm:ss`.
+    Duration(Duration),
+    Error(CellError),
     Blank,
 }
 
@@ -13,10 +38,185 @@ impl CellValue {
     pub fn text<S: Into<String>>(v: S) -> Self {
         CellValue::Text(v.into())
     }
+    pub fn rich(v: Vec<TextRun>) -> Self {
+        CellValue::RichText(v)
+    }
     pub fn bool(v: bool) -> Self {
         CellValue::Bool(v)
     }
     pub fn formula<S: Into<String>>(v: S) -> Self {
-        CellValue::Formula(v.into())
+        CellValue::Formula(Formula::new(v))
+    }
+    pub fn url<S: Into<String>>(v: S) -> Self {
+        CellValue::Hyperlink(Hyperlink::url(v))
+    }
+    pub fn link(v: Hyperlink) -> Self {
+        CellValue::Hyperlink(v)
+    }
+    pub fn date(v: Date) -> Self {
+        CellValue::Date(v)
+    }
+    pub fn time(v: Time) -> Self {
+        CellValue::Time(v)
+    }
+    pub fn datetime(v: DateTime) -> Self {
+        CellValue::DateTime(v)
+    }
+    pub fn duration(v: Duration) -> Self {
+        CellValue::Duration(v)
+    }
+    pub fn error(v: CellError) -> Self {
+        CellValue::Error(v)
+    }
+}
+
+impl From<Formula> for CellValue {
+    fn from(v: Formula) -> Self {
+        CellValue::Formula(v)
+    }
+}
+
+impl From<CellError> for CellValue {
+    fn from(v: CellError) -> Self {
+        CellValue::Error(v)
+    }
+}
+
+/// Excel error values such as `#DIV/0!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellError {
+    Null,
+    Div0,
+    Value,
+    Ref,
+    Name,
+    Num,
+    NotAvailable,
+    GettingData,
+}
+
+impl CellError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CellError::Null => "#NULL!",
+            CellError::Div0 => "#DIV/0!",
+            CellError::Value => "#VALUE!",
+            CellError::Ref => "#REF!",
+            CellError::Name => "#NAME?",
+            CellError::Num => "#NUM!",
+            CellError::NotAvailable => "#N/A",
+            CellError::GettingData => "#GETTING_DATA",
+        }
+    }
+}
+
+/// What to write in place of a NaN or infinite number, which Excel cannot store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Write an error cell, or an error result for a formula.
+    Error(CellError),
+    /// Write an empty cell, or no cached result for a formula.
+    Blank,
+    /// Fail the write with `MrXlsxError::InvalidArgument`.
+    Reject,
+}
+
+impl Default for NonFinitePolicy {
+    fn default() -> Self {
+        NonFinitePolicy::Error(CellError::Num)
+    }
+}
+
+impl NonFinitePolicy {
+    /// Replacement for `value` if it holds a non-finite number.
+    pub(crate) fn replace(self, value: &CellValue) -> Result<Option<CellValue>> {
+        let n = match value {
+            CellValue::Number(n) => *n,
+            CellValue::Formula(Formula {
+                result: Some(FormulaResult::Number(n)),
+                ..
+            }) => *n,
+            _ => return Ok(None),
+        };
+        if n.is_finite() {
+            return Ok(None);
+        }
+        let replacement = match (self, value) {
+            (NonFinitePolicy::Reject, _) => {
+                return Err(MrXlsxError::InvalidArgument(format!(
+                    "{n} is not a number Excel can store"
+                )));
+            }
+            (NonFinitePolicy::Error(e), CellValue::Formula(formula)) => {
+                CellValue::Formula(Formula {
+                    result: Some(FormulaResult::Error(e)),
+                    ..formula.clone()
+                })
+            }
+            (NonFinitePolicy::Blank, CellValue::Formula(formula)) => CellValue::Formula(Formula {
+                result: None,
+                ..formula.clone()
+            }),
+            (NonFinitePolicy::Error(e), _) => CellValue::Error(e),
+            (NonFinitePolicy::Blank, _) => CellValue::Blank,
+        };
+        Ok(Some(replacement))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finite_values_are_kept() {
+        let policy = NonFinitePolicy::Reject;
+        assert_eq!(policy.replace(&CellValue::Number(1.5)).unwrap(), None);
+        assert_eq!(policy.replace(&CellValue::text("NaN")).unwrap(), None);
+        let formula = CellValue::Formula(Formula::new("A1").result(2.0));
+        assert_eq!(policy.replace(&formula).unwrap(), None);
+    }
+
+    #[test]
+    fn non_finite_numbers_follow_the_policy() {
+        let nan = CellValue::Number(f64::NAN);
+        assert_eq!(
+            NonFinitePolicy::default().replace(&nan).unwrap(),
+            Some(CellValue::Error(CellError::Num))
+        );
+        assert_eq!(
+            NonFinitePolicy::Error(CellError::NotAvailable)
+                .replace(&CellValue::Number(f64::INFINITY))
+                .unwrap(),
+            Some(CellValue::Error(CellError::NotAvailable))
+        );
+        assert_eq!(
+            NonFinitePolicy::Blank.replace(&nan).unwrap(),
+            Some(CellValue::Blank)
+        );
+        assert!(matches!(
+            NonFinitePolicy::Reject.replace(&CellValue::Number(f64::NEG_INFINITY)),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn non_finite_formula_results_follow_the_policy() {
+        let formula = Formula::new("1/0").result(f64::INFINITY);
+        let value = CellValue::Formula(formula.clone());
+        assert_eq!(
+            NonFinitePolicy::Error(CellError::Div0)
+                .replace(&value)
+                .unwrap(),
+            Some(CellValue::Formula(Formula {
+                result: Some(FormulaResult::Error(CellError::Div0)),
+                ..formula.clone()
+            }))
+        );
+        assert_eq!(
+            NonFinitePolicy::Blank.replace(&value).unwrap(),
+            Some(CellValue::Formula(Formula::new("1/0")))
+        );
+        assert!(NonFinitePolicy::Reject.replace(&value).is_err());
     }
 }
@@ -0,0 +1,425 @@
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{
+        image::ObjectAnchor, quote_sheet_name, range::CellRange, style::color::Color, xml_escape,
+    },
+};
+
+// ids linking the plot to its axes, only need to be unique within a chart
+const CATEGORY_AXIS_ID: u32 = 50010001;
+const VALUE_AXIS_ID: u32 = 50010002;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartType {
+    Column,
+    Bar,
+    Line,
+    Pie,
+    Scatter,
+    Area,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LegendPosition {
+    #[default]
+    Right,
+    Left,
+    Top,
+    Bottom,
+    TopRight,
+}
+
+impl LegendPosition {
+    fn as_xml_attr(self) -> &'static str {
+        match self {
+            LegendPosition::Right => "r",
+            LegendPosition::Left => "l",
+            LegendPosition::Top => "t",
+            LegendPosition::Bottom => "b",
+            LegendPosition::TopRight => "tr",
+        }
+    }
+}
+
+/// Absolute reference to a block of cells for use in a chart series, e.g.
+/// `'Sheet (1)'!$B$2:$B$10`.
+pub fn range_ref(
+    sheet: &str,
+    first_row: u32,
+    first_col: u32,
+    last_row: u32,
+    last_col: u32,
+) -> Result<String> {
+    let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+    Ok(format!(
+        "{}!{}",
+        quote_sheet_name(sheet),
+        range.to_absolute_ref()
+    ))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartSeries {
+    /// Reference to the values, see [`range_ref`].
+    pub values: String,
+    /// Reference to the category labels, or the x values of a scatter chart.
+    pub categories: Option<String>,
+    pub name: Option<SeriesName>,
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeriesName {
+    Text(String),
+    /// Reference to the cell holding the name.
+    Ref(String),
+}
+
+impl ChartSeries {
+    pub fn new<S: Into<String>>(values: S) -> Self {
+        ChartSeries {
+            values: values.into(),
+            ..ChartSeries::default()
+        }
+    }
+
+    pub fn categories<S: Into<String>>(mut self, categories: S) -> Self {
+        self.categories = Some(categories.into());
+        self
+    }
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(SeriesName::Text(name.into()));
+        self
+    }
+    pub fn name_ref<S: Into<String>>(mut self, cell: S) -> Self {
+        self.name = Some(SeriesName::Ref(cell.into()));
+        self
+    }
+    pub fn color(mut self, hex: &str) -> Self {
+        self.color = Some(Color::new(hex));
+        self
+    }
+
+    fn to_xml(&self, i: usize, chart_type: ChartType) -> String {
+        let mut xml = format!("<c:ser><c:idx val=\"{i}\"/><c:order val=\"{i}\"/>");
+        match &self.name {
+            Some(SeriesName::Text(text)) => {
+                xml.push_str(&format!("<c:tx><c:v>{}</c:v></c:tx>", xml_escape(text)))
+            }
+            Some(SeriesName::Ref(f)) => xml.push_str(&format!(
+                "<c:tx><c:strRef><c:f>{}</c:f></c:strRef></c:tx>",
+                xml_escape(formula(f))
+            )),
+            None => {}
+        }
+
+        let lines = matches!(chart_type, ChartType::Line | ChartType::Scatter);
+        if let Some(color) = &self.color {
+            // drop the alpha channel
+            let argb = color.as_argb();
+            let fill = format!(
+                "<a:solidFill><a:srgbClr val=\"{}\"/></a:solidFill>",
+                &argb[argb.len().saturating_sub(6)..]
+            );
+            if lines {
+                xml.push_str(&format!("<c:spPr><a:ln w=\"28575\">{fill}</a:ln></c:spPr>"));
+            } else {
+                xml.push_str(&format!("<c:spPr>{fill}</c:spPr>"));
+            }
+        }
+        match chart_type {
+            ChartType::Column | ChartType::Bar => xml.push_str("<c:invertIfNegative val=\"0\"/>"),
+            ChartType::Line | ChartType::Scatter => {
+                xml.push_str("<c:marker><c:symbol val=\"none\"/></c:marker>")
+            }
+            ChartType::Pie | ChartType::Area => {}
+        }
+
+        let values = xml_escape(formula(&self.values));
+        if chart_type == ChartType::Scatter {
+            if let Some(x) = &self.categories {
+                xml.push_str(&format!(
+                    "<c:xVal><c:numRef><c:f>{}</c:f></c:numRef></c:xVal>",
+                    xml_escape(formula(x))
+                ));
+            }
+            xml.push_str(&format!(
+                "<c:yVal><c:numRef><c:f>{values}</c:f></c:numRef></c:yVal><c:smooth val=\"0\"/>"
+            ));
+        } else {
+            if let Some(categories) = &self.categories {
+                xml.push_str(&format!(
+                    "<c:cat><c:strRef><c:f>{}</c:f></c:strRef></c:cat>",
+                    xml_escape(formula(categories))
+                ));
+            }
+            xml.push_str(&format!(
+                "<c:val><c:numRef><c:f>{values}</c:f></c:numRef></c:val>"
+            ));
+            if chart_type == ChartType::Line {
+                xml.push_str("<c:smooth val=\"0\"/>");
+            }
+        }
+        xml.push_str("</c:ser>");
+        xml
+    }
+}
+
+/// A chart over ranges of written sheets, placed on a worksheet with
+/// `SheetWriter::insert_chart` or on its own tab with `Workbook::add_chartsheet`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub chart_type: ChartType,
+    pub title: Option<String>,
+    pub x_axis_title: Option<String>,
+    pub y_axis_title: Option<String>,
+    /// `None` hides the legend.
+    pub legend: Option<LegendPosition>,
+    pub series: Vec<ChartSeries>,
+    /// Size in pixels when placed on a worksheet.
+    pub width: u32,
+    pub height: u32,
+    /// Offset from the top-left corner of the anchor cell, in pixels.
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub anchor: ObjectAnchor,
+}
+
+impl Chart {
+    pub fn new(chart_type: ChartType) -> Self {
+        Chart {
+            chart_type,
+            title: None,
+            x_axis_title: None,
+            y_axis_title: None,
+            legend: Some(LegendPosition::default()),
+            series: Vec::new(),
+            width: 480,
+            height: 288,
+            x_offset: 0,
+            y_offset: 0,
+            anchor: ObjectAnchor::TwoCell,
+        }
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    pub fn x_axis_title<S: Into<String>>(mut self, title: S) -> Self {
+        self.x_axis_title = Some(title.into());
+        self
+    }
+    pub fn y_axis_title<S: Into<String>>(mut self, title: S) -> Self {
+        self.y_axis_title = Some(title.into());
+        self
+    }
+    pub fn legend(mut self, position: LegendPosition) -> Self {
+        self.legend = Some(position);
+        self
+    }
+    pub fn no_legend(mut self) -> Self {
+        self.legend = None;
+        self
+    }
+    pub fn add_series(mut self, series: ChartSeries) -> Self {
+        self.series.push(series);
+        self
+    }
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+    pub fn offset(mut self, x: u32, y: u32) -> Self {
+        self.x_offset = x;
+        self.y_offset = y;
+        self
+    }
+    pub fn anchor(mut self, anchor: ObjectAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.series.is_empty() {
+            return Err(MrXlsxError::InvalidArgument("chart has no series".into()));
+        }
+        if let Some(series) = self.series.iter().find(|s| formula(&s.values).is_empty()) {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "chart series {:?} has no values",
+                series.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// `xl/charts/chartN.xml`.
+    pub(crate) fn to_xml(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<c:chartSpace xmlns:c="http://schemas.openxmlformats.org/drawingml/2006/chart" "#,
+            r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            r#"<c:roundedCorners val="0"/><c:chart>"#,
+        ));
+        match &self.title {
+            Some(title) => xml.push_str(&title_xml(title)),
+            None => xml.push_str("<c:autoTitleDeleted val=\"1\"/>"),
+        }
+
+        xml.push_str("<c:plotArea><c:layout/>");
+        xml.push_str(&self.plot_xml());
+        xml.push_str(&self.axes_xml());
+        xml.push_str("</c:plotArea>");
+
+        if let Some(position) = self.legend {
+            xml.push_str(&format!(
+                "<c:legend><c:legendPos val=\"{}\"/><c:overlay val=\"0\"/></c:legend>",
+                position.as_xml_attr()
+            ));
+        }
+        xml.push_str("<c:plotVisOnly val=\"1\"/></c:chart></c:chartSpace>");
+        xml
+    }
+
+    fn plot_xml(&self) -> String {
+        let series: String = self
+            .series
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.to_xml(i, self.chart_type))
+            .collect();
+        let axes = format!("<c:axId val=\"{CATEGORY_AXIS_ID}\"/><c:axId val=\"{VALUE_AXIS_ID}\"/>");
+        match self.chart_type {
+            ChartType::Column | ChartType::Bar => format!(
+                concat!(
+                    r#"<c:barChart><c:barDir val="{}"/><c:grouping val="clustered"/>"#,
+                    r#"<c:varyColors val="0"/>{}<c:gapWidth val="150"/>{}</c:barChart>"#,
+                ),
+                if self.chart_type == ChartType::Bar {
+                    "bar"
+                } else {
+                    "col"
+                },
+                series,
+                axes
+            ),
+            ChartType::Line => format!(
+                concat!(
+                    r#"<c:lineChart><c:grouping val="standard"/><c:varyColors val="0"/>"#,
+                    r#"{}<c:marker val="1"/>{}</c:lineChart>"#,
+                ),
+                series, axes
+            ),
+            ChartType::Area => format!(
+                concat!(
+                    r#"<c:areaChart><c:grouping val="standard"/><c:varyColors val="0"/>"#,
+                    r#"{}{}</c:areaChart>"#,
+                ),
+                series, axes
+            ),
+            ChartType::Scatter => format!(
+                concat!(
+                    r#"<c:scatterChart><c:scatterStyle val="lineMarker"/><c:varyColors val="0"/>"#,
+                    r#"{}{}</c:scatterChart>"#,
+                ),
+                series, axes
+            ),
+            ChartType::Pie => format!(
+                r#"<c:pieChart><c:varyColors val="1"/>{series}<c:firstSliceAng val="0"/></c:pieChart>"#
+            ),
+        }
+    }
+
+    fn axes_xml(&self) -> String {
+        // the category axis runs along the bottom except for horizontal bars
+        let (x_pos, y_pos) = match self.chart_type {
+            ChartType::Pie => return String::new(),
+            ChartType::Bar => ("l", "b"),
+            _ => ("b", "l"),
+        };
+        let x_title = self
+            .x_axis_title
+            .as_deref()
+            .map(title_xml)
+            .unwrap_or_default();
+        let y_title = self
+            .y_axis_title
+            .as_deref()
+            .map(title_xml)
+            .unwrap_or_default();
+
+        let x_axis = if self.chart_type == ChartType::Scatter {
+            value_axis_xml(
+                CATEGORY_AXIS_ID,
+                VALUE_AXIS_ID,
+                x_pos,
+                &x_title,
+                false,
+                "midCat",
+            )
+        } else {
+            format!(
+                concat!(
+                    r#"<c:catAx><c:axId val="{}"/><c:scaling><c:orientation val="minMax"/></c:scaling>"#,
+                    r#"<c:delete val="0"/><c:axPos val="{}"/>{}"#,
+                    r#"<c:numFmt formatCode="General" sourceLinked="1"/><c:majorTickMark val="out"/>"#,
+                    r#"<c:minorTickMark val="none"/><c:tickLblPos val="nextTo"/><c:crossAx val="{}"/>"#,
+                    r#"<c:crosses val="autoZero"/><c:auto val="1"/><c:lblAlgn val="ctr"/>"#,
+                    r#"<c:lblOffset val="100"/></c:catAx>"#,
+                ),
+                CATEGORY_AXIS_ID, x_pos, x_title, VALUE_AXIS_ID
+            )
+        };
+        let y_axis = value_axis_xml(
+            VALUE_AXIS_ID,
+            CATEGORY_AXIS_ID,
+            y_pos,
+            &y_title,
+            true,
+            "between",
+        );
+        x_axis + &y_axis
+    }
+}
+
+fn value_axis_xml(
+    id: u32,
+    cross_id: u32,
+    position: &str,
+    title: &str,
+    gridlines: bool,
+    cross_between: &str,
+) -> String {
+    format!(
+        concat!(
+            r#"<c:valAx><c:axId val="{}"/><c:scaling><c:orientation val="minMax"/></c:scaling>"#,
+            r#"<c:delete val="0"/><c:axPos val="{}"/>{}{}"#,
+            r#"<c:numFmt formatCode="General" sourceLinked="1"/><c:majorTickMark val="out"/>"#,
+            r#"<c:minorTickMark val="none"/><c:tickLblPos val="nextTo"/><c:crossAx val="{}"/>"#,
+            r#"<c:crosses val="autoZero"/><c:crossBetween val="{}"/></c:valAx>"#,
+        ),
+        id,
+        position,
+        if gridlines { "<c:majorGridlines/>" } else { "" },
+        title,
+        cross_id,
+        cross_between
+    )
+}
+
+fn title_xml(text: &str) -> String {
+    format!(
+        concat!(
+            r#"<c:title><c:tx><c:rich><a:bodyPr/><a:lstStyle/><a:p><a:r><a:t>{}</a:t></a:r></a:p>"#,
+            r#"</c:rich></c:tx><c:overlay val="0"/></c:title>"#,
+        ),
+        xml_escape(text)
+    )
+}
+
+fn formula(f: &str) -> &str {
+    f.trim().trim_start_matches('=')
+}
@@ -0,0 +1,113 @@
+pub(crate) const DEFAULT_COLUMN_WIDTH: f64 = 8.43;
+pub(crate) const MAX_COLUMN_WIDTH: f64 = 255.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnOptions {
+    pub width: Option<f64>,
+    pub hidden: bool,
+    pub outline_level: u8,
+    pub collapsed: bool,
+}
+
+impl ColumnOptions {
+    pub fn new() -> Self {
+        ColumnOptions::default()
+    }
+
+    /// Width in characters of the default font, as shown in Excel's column width dialog.
+    /// A NaN or infinite width leaves the column at its default width.
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width
+            .is_finite()
+            .then(|| width.clamp(0.0, MAX_COLUMN_WIDTH));
+        self
+    }
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+    /// Outline (grouping) level, 1-7.
+    pub fn outline_level(mut self, level: u8) -> Self {
+        self.outline_level = level.min(7);
+        self
+    }
+    pub fn collapsed(mut self) -> Self {
+        self.collapsed = true;
+        self
+    }
+
+    // the width set by the caller, if it can be written
+    pub(crate) fn custom_width(&self) -> Option<f64> {
+        self.width.filter(|width| width.is_finite())
+    }
+
+    pub(crate) fn to_xml(&self, col: u32, auto_width: Option<f64>) -> String {
+        let n = col + 1;
+        let width = self.custom_width().or(auto_width);
+        let mut xml = format!(
+            "<col min=\"{n}\" max=\"{n}\" width=\"{}\"",
+            width.unwrap_or(DEFAULT_COLUMN_WIDTH)
+        );
+        if width.is_some() {
+            xml.push_str(" customWidth=\"1\"");
+        }
+        if self.hidden {
+            xml.push_str(" hidden=\"1\"");
+        }
+        if self.outline_level > 0 {
+            xml.push_str(&format!(" outlineLevel=\"{}\"", self.outline_level));
+        }
+        if self.collapsed {
+            xml.push_str(" collapsed=\"1\"");
+        }
+        xml.push_str("/>");
+        xml
+    }
+}
+
+/// Pixel width of a column `width` characters wide.
+pub(crate) fn width_to_pixels(width: f64) -> u32 {
+    if width < 1.0 {
+        (width * 12.0).round() as u32
+    } else {
+        (width * 7.0 + 5.0) as u32
+    }
+}
+
+/// Approximate column width for `chars` characters of the default font, never
+/// narrower than a default column.
+pub(crate) fn auto_fit_width(chars: usize) -> f64 {
+    let width = (chars as f64 * 1.1 + 2.0).clamp(DEFAULT_COLUMN_WIDTH, MAX_COLUMN_WIDTH);
+    (width * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_fit_width_is_clamped() {
+        assert_eq!(auto_fit_width(1), DEFAULT_COLUMN_WIDTH);
+        assert_eq!(auto_fit_width(20), 24.0);
+        assert_eq!(auto_fit_width(1_000), MAX_COLUMN_WIDTH);
+    }
+
+    #[test]
+    fn non_finite_widths_are_ignored() {
+        for width in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(ColumnOptions::new().width(width).width, None);
+            let options = ColumnOptions {
+                width: Some(width),
+                ..ColumnOptions::default()
+            };
+            assert_eq!(
+                options.to_xml(0, None),
+                r#"<col min="1" max="1" width="8.43"/>"#
+            );
+        }
+        assert_eq!(
+            ColumnOptions::new().width(300.0).width,
+            Some(MAX_COLUMN_WIDTH)
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use crate::workbook::{
+    style::{Style, color::Color},
+    validation::ValidationCriteria,
+    xml_escape,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconSetType {
+    ThreeArrows,
+    ThreeArrowsGray,
+    ThreeFlags,
+    ThreeTrafficLights,
+    ThreeSigns,
+    ThreeSymbols,
+    FourArrows,
+    FourRating,
+    FourTrafficLights,
+    FiveArrows,
+    FiveRating,
+    FiveQuarters,
+}
+
+impl IconSetType {
+    fn as_xml_attr(&self) -> &str {
+        match self {
+            IconSetType::ThreeArrows => "3Arrows",
+            IconSetType::ThreeArrowsGray => "3ArrowsGray",
+            IconSetType::ThreeFlags => "3Flags",
+            IconSetType::ThreeTrafficLights => "3TrafficLights1",
+            IconSetType::ThreeSigns => "3Signs",
+            IconSetType::ThreeSymbols => "3Symbols",
+            IconSetType::FourArrows => "4Arrows",
+            IconSetType::FourRating => "4Rating",
+            IconSetType::FourTrafficLights => "4TrafficLights",
+            IconSetType::FiveArrows => "5Arrows",
+            IconSetType::FiveRating => "5Rating",
+            IconSetType::FiveQuarters => "5Quarters",
+        }
+    }
+
+    fn icon_count(&self) -> u32 {
+        match self {
+            IconSetType::ThreeArrows
+            | IconSetType::ThreeArrowsGray
+            | IconSetType::ThreeFlags
+            | IconSetType::ThreeTrafficLights
+            | IconSetType::ThreeSigns
+            | IconSetType::ThreeSymbols => 3,
+            IconSetType::FourArrows | IconSetType::FourRating | IconSetType::FourTrafficLights => 4,
+            IconSetType::FiveArrows | IconSetType::FiveRating | IconSetType::FiveQuarters => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionalFormatRule {
+    /// Compare the cell value, using the same criteria as data validation.
+    CellValue(ValidationCriteria),
+    /// Formula relative to the top-left cell of the range, e.g. `$C2>100`.
+    Formula(String),
+    Top {
+        rank: u32,
+        percent: bool,
+    },
+    Bottom {
+        rank: u32,
+        percent: bool,
+    },
+    DuplicateValues,
+    UniqueValues,
+    ColorScale2 {
+        min: Color,
+        max: Color,
+    },
+    ColorScale3 {
+        min: Color,
+        mid: Color,
+        max: Color,
+    },
+    DataBar {
+        color: Color,
+    },
+    IconSet {
+        icons: IconSetType,
+        reverse: bool,
+        show_value: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalFormat {
+    pub rule: ConditionalFormatRule,
+    /// Differential format applied when the rule matches. Only the parts that differ
+    /// from the default style are written.
+    pub style: Option<Style>,
+    pub stop_if_true: bool,
+}
+
+impl ConditionalFormat {
+    pub fn new(rule: ConditionalFormatRule) -> Self {
+        ConditionalFormat {
+            rule,
+            style: None,
+            stop_if_true: false,
+        }
+    }
+
+    pub fn cell_value(criteria: ValidationCriteria) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::CellValue(criteria))
+    }
+    pub fn formula<S: Into<String>>(formula: S) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::Formula(formula.into()))
+    }
+    pub fn top(rank: u32) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::Top {
+            rank,
+            percent: false,
+        })
+    }
+    pub fn top_percent(rank: u32) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::Top {
+            rank,
+            percent: true,
+        })
+    }
+    pub fn bottom(rank: u32) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::Bottom {
+            rank,
+            percent: false,
+        })
+    }
+    pub fn bottom_percent(rank: u32) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::Bottom {
+            rank,
+            percent: true,
+        })
+    }
+    pub fn duplicates() -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::DuplicateValues)
+    }
+    pub fn unique() -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::UniqueValues)
+    }
+    pub fn color_scale_2(min: &str, max: &str) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::ColorScale2 {
+            min: Color::new(min),
+            max: Color::new(max),
+        })
+    }
+    pub fn color_scale_3(min: &str, mid: &str, max: &str) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::ColorScale3 {
+            min: Color::new(min),
+            mid: Color::new(mid),
+            max: Color::new(max),
+        })
+    }
+    pub fn data_bar(color: &str) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::DataBar {
+            color: Color::new(color),
+        })
+    }
+    pub fn icon_set(icons: IconSetType) -> Self {
+        ConditionalFormat::new(ConditionalFormatRule::IconSet {
+            icons,
+            reverse: false,
+            show_value: true,
+        })
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+    pub fn stop_if_true(mut self) -> Self {
+        self.stop_if_true = true;
+        self
+    }
+
+    pub(crate) fn to_xml(&self, priority: u32, dxf_id: Option<usize>) -> String {
+        let mut xml = String::from("<cfRule");
+        let kind = match &self.rule {
+            ConditionalFormatRule::CellValue(_) => "cellIs",
+            ConditionalFormatRule::Formula(_) => "expression",
+            ConditionalFormatRule::Top { .. } | ConditionalFormatRule::Bottom { .. } => "top10",
+            ConditionalFormatRule::DuplicateValues => "duplicateValues",
+            ConditionalFormatRule::UniqueValues => "uniqueValues",
+            ConditionalFormatRule::ColorScale2 { .. }
+            | ConditionalFormatRule::ColorScale3 { .. } => "colorScale",
+            ConditionalFormatRule::DataBar { .. } => "dataBar",
+            ConditionalFormatRule::IconSet { .. } => "iconSet",
+        };
+        xml.push_str(&format!(" type=\"{kind}\""));
+        if let Some(id) = dxf_id {
+            xml.push_str(&format!(" dxfId=\"{id}\""));
+        }
+        xml.push_str(&format!(" priority=\"{priority}\""));
+        if self.stop_if_true {
+            xml.push_str(" stopIfTrue=\"1\"");
+        }
+
+        match &self.rule {
+            ConditionalFormatRule::CellValue(criteria) => {
+                let (a, b) = criteria.formulas();
+                xml.push_str(&format!(" operator=\"{}\">", criteria.operator()));
+                xml.push_str(&format!("<formula>{}</formula>", xml_escape(a)));
+                if let Some(b) = b {
+                    xml.push_str(&format!("<formula>{}</formula>", xml_escape(b)));
+                }
+            }
+            ConditionalFormatRule::Formula(f) => {
+                let f = xml_escape(f.trim_start_matches('='));
+                xml.push_str(&format!("><formula>{f}</formula>"));
+            }
+            ConditionalFormatRule::Top { rank, percent }
+            | ConditionalFormatRule::Bottom { rank, percent } => {
+                if *percent {
+                    xml.push_str(" percent=\"1\"");
+                }
+                if matches!(self.rule, ConditionalFormatRule::Bottom { .. }) {
+                    xml.push_str(" bottom=\"1\"");
+                }
+                xml.push_str(&format!(" rank=\"{rank}\"/>"));
+                return xml;
+            }
+            ConditionalFormatRule::DuplicateValues | ConditionalFormatRule::UniqueValues => {
+                xml.push_str("/>");
+                return xml;
+            }
+            ConditionalFormatRule::ColorScale2 { min, max } => {
+                xml.push_str(&format!(
+                    "><colorScale><cfvo type=\"min\"/><cfvo type=\"max\"/><color rgb=\"{}\"/><color rgb=\"{}\"/></colorScale>",
+                    min.as_argb(),
+                    max.as_argb()
+                ));
+            }
+            ConditionalFormatRule::ColorScale3 { min, mid, max } => {
+                xml.push_str(&format!(
+                    "><colorScale><cfvo type=\"min\"/><cfvo type=\"percentile\" val=\"50\"/><cfvo type=\"max\"/><color rgb=\"{}\"/><color rgb=\"{}\"/><color rgb=\"{}\"/></colorScale>",
+                    min.as_argb(),
+                    mid.as_argb(),
+                    max.as_argb()
+                ));
+            }
+            ConditionalFormatRule::DataBar { color } => {
+                xml.push_str(&format!(
+                    "><dataBar><cfvo type=\"min\"/><cfvo type=\"max\"/><color rgb=\"{}\"/></dataBar>",
+                    color.as_argb()
+                ));
+            }
+            ConditionalFormatRule::IconSet {
+                icons,
+                reverse,
+                show_value,
+            } => {
+                xml.push_str(&format!("><iconSet iconSet=\"{}\"", icons.as_xml_attr()));
+                if !show_value {
+                    xml.push_str(" showValue=\"0\"");
+                }
+                if *reverse {
+                    xml.push_str(" reverse=\"1\"");
+                }
+                xml.push('>');
+                let n = icons.icon_count();
+                for i in 0..n {
+                    xml.push_str(&format!(
+                        "<cfvo type=\"percent\" val=\"{}\"/>",
+                        (i * 100 + n / 2) / n
+                    ));
+                }
+                xml.push_str("</iconSet>");
+            }
+        }
+        xml.push_str("</cfRule>");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_xml() {
+        let rule = ConditionalFormat::cell_value(ValidationCriteria::between(1, 10));
+        assert_eq!(
+            rule.to_xml(1, Some(0)),
+            concat!(
+                r#"<cfRule type="cellIs" dxfId="0" priority="1" operator="between">"#,
+                "<formula>1</formula><formula>10</formula></cfRule>"
+            )
+        );
+        assert_eq!(
+            ConditionalFormat::formula("=$A1<>\"\"")
+                .stop_if_true()
+                .to_xml(2, Some(3)),
+            concat!(
+                r#"<cfRule type="expression" dxfId="3" priority="2" stopIfTrue="1">"#,
+                "<formula>$A1&lt;&gt;&quot;&quot;</formula></cfRule>"
+            )
+        );
+        assert_eq!(
+            ConditionalFormat::bottom_percent(10).to_xml(3, None),
+            r#"<cfRule type="top10" priority="3" percent="1" bottom="1" rank="10"/>"#
+        );
+        assert_eq!(
+            ConditionalFormat::duplicates().to_xml(4, Some(1)),
+            r#"<cfRule type="duplicateValues" dxfId="1" priority="4"/>"#
+        );
+        assert_eq!(
+            ConditionalFormat::color_scale_2("F8696B", "#63BE7B").to_xml(5, None),
+            concat!(
+                r#"<cfRule type="colorScale" priority="5"><colorScale><cfvo type="min"/><cfvo type="max"/>"#,
+                r#"<color rgb="FFF8696B"/><color rgb="FF63BE7B"/></colorScale></cfRule>"#
+            )
+        );
+        assert_eq!(
+            ConditionalFormat::data_bar("638EC6").to_xml(6, None),
+            concat!(
+                r#"<cfRule type="dataBar" priority="6"><dataBar><cfvo type="min"/><cfvo type="max"/>"#,
+                r#"<color rgb="FF638EC6"/></dataBar></cfRule>"#
+            )
+        );
+    }
+
+    #[test]
+    fn icon_set_thresholds() {
+        let rule = ConditionalFormat::new(ConditionalFormatRule::IconSet {
+            icons: IconSetType::ThreeArrows,
+            reverse: true,
+            show_value: false,
+        });
+        assert_eq!(
+            rule.to_xml(1, None),
+            concat!(
+                r#"<cfRule type="iconSet" priority="1"><iconSet iconSet="3Arrows" showValue="0" reverse="1">"#,
+                r#"<cfvo type="percent" val="0"/><cfvo type="percent" val="33"/><cfvo type="percent" val="67"/>"#,
+                "</iconSet></cfRule>"
+            )
+        );
+        let xml = ConditionalFormat::icon_set(IconSetType::FiveRating).to_xml(1, None);
+        assert!(
+            xml.contains(r#"<cfvo type="percent" val="20"/><cfvo type="percent" val="40"/>"#),
+            "{xml}"
+        );
+    }
+}
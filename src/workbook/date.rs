@@ -0,0 +1,350 @@
+use crate::{Result, error::MrXlsxError};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// A calendar date between 1900-01-01 and 9999-12-31.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self> {
+        if !(1900..=9999).contains(&year)
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+        {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "{year:04}-{month:02}-{day:02} is not a date Excel can store"
+            )));
+        }
+        Ok(Date { year, month, day })
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Excel serial number of the date.
+    pub(crate) fn serial(&self, date_1904: bool) -> Result<f64> {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        if date_1904 {
+            // day 0 is 1904-01-01
+            let serial = days - days_from_civil(1904, 1, 1);
+            if serial < 0 {
+                return Err(MrXlsxError::InvalidArgument(format!(
+                    "{self} is before 1904-01-01, the first date of the 1904 date system"
+                )));
+            }
+            return Ok(serial as f64);
+        }
+        // day 1 is 1900-01-01, and Excel counts a 1900-02-29 that never existed
+        let serial = days - days_from_civil(1899, 12, 31);
+        if serial < 60 {
+            Ok(serial as f64)
+        } else {
+            Ok(serial as f64 + 1.0)
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+}
+
+impl Time {
+    pub fn new(hour: u8, minute: u8, second: u8) -> Result<Self> {
+        Time::with_nanos(hour, minute, second, 0)
+    }
+
+    pub fn with_millis(hour: u8, minute: u8, second: u8, millis: u16) -> Result<Self> {
+        if millis > 999 {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "{hour:02}:{minute:02}:{second:02}.{millis:03} is not a time of day"
+            )));
+        }
+        Time::with_nanos(hour, minute, second, millis as u32 * 1_000_000)
+    }
+
+    pub fn with_nanos(hour: u8, minute: u8, second: u8, nanosecond: u32) -> Result<Self> {
+        if hour > 23 || minute > 59 || second > 59 || nanosecond >= 1_000_000_000 {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "{hour:02}:{minute:02}:{second:02}.{nanosecond:09} is not a time of day"
+            )));
+        }
+        Ok(Time {
+            hour,
+            minute,
+            second,
+            nanosecond,
+        })
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+
+    /// Fraction of the day, the Excel serial number of the time.
+    pub(crate) fn serial(&self) -> f64 {
+        let seconds = self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32;
+        (seconds as f64 + self.nanosecond as f64 / 1e9) / SECONDS_PER_DAY
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+impl DateTime {
+    pub fn new(date: Date, time: Time) -> Self {
+        DateTime { date, time }
+    }
+
+    pub(crate) fn serial(&self, date_1904: bool) -> Result<f64> {
+        Ok(self.date.serial(date_1904)? + self.time.serial())
+    }
+}
+
+/// Length of `duration` in days, the Excel serial number of an elapsed time.
+pub(crate) fn duration_serial(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() / SECONDS_PER_DAY
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+    use super::{Date, DateTime, Time};
+    use crate::{error::MrXlsxError, workbook::cell::CellValue};
+
+    impl TryFrom<NaiveDate> for Date {
+        type Error = MrXlsxError;
+
+        fn try_from(date: NaiveDate) -> Result<Self, Self::Error> {
+            let year = u16::try_from(date.year()).map_err(|_| {
+                MrXlsxError::InvalidArgument(format!("{date} is not a date Excel can store"))
+            })?;
+            Date::new(year, date.month() as u8, date.day() as u8)
+        }
+    }
+
+    impl From<NaiveTime> for Time {
+        fn from(time: NaiveTime) -> Self {
+            // chrono keeps leap seconds as nanoseconds past 1e9
+            let nanosecond = time.nanosecond().min(999_999_999);
+            Time {
+                hour: time.hour() as u8,
+                minute: time.minute() as u8,
+                second: time.second() as u8,
+                nanosecond,
+            }
+        }
+    }
+
+    impl TryFrom<NaiveDateTime> for DateTime {
+        type Error = MrXlsxError;
+
+        fn try_from(datetime: NaiveDateTime) -> Result<Self, Self::Error> {
+            Ok(DateTime::new(
+                datetime.date().try_into()?,
+                datetime.time().into(),
+            ))
+        }
+    }
+
+    impl TryFrom<NaiveDate> for CellValue {
+        type Error = MrXlsxError;
+
+        fn try_from(date: NaiveDate) -> Result<Self, MrXlsxError> {
+            Ok(CellValue::Date(date.try_into()?))
+        }
+    }
+
+    impl From<NaiveTime> for CellValue {
+        fn from(time: NaiveTime) -> Self {
+            CellValue::Time(time.into())
+        }
+    }
+
+    impl TryFrom<NaiveDateTime> for CellValue {
+        type Error = MrXlsxError;
+
+        fn try_from(datetime: NaiveDateTime) -> Result<Self, MrXlsxError> {
+            Ok(CellValue::DateTime(datetime.try_into()?))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use super::{Date, DateTime, Time};
+    use crate::{error::MrXlsxError, workbook::cell::CellValue};
+
+    impl TryFrom<time::Date> for Date {
+        type Error = MrXlsxError;
+
+        fn try_from(date: time::Date) -> Result<Self, Self::Error> {
+            let year = u16::try_from(date.year()).map_err(|_| {
+                MrXlsxError::InvalidArgument(format!("{date} is not a date Excel can store"))
+            })?;
+            Date::new(year, date.month() as u8, date.day())
+        }
+    }
+
+    impl From<time::Time> for Time {
+        fn from(time: time::Time) -> Self {
+            Time {
+                hour: time.hour(),
+                minute: time.minute(),
+                second: time.second(),
+                nanosecond: time.nanosecond(),
+            }
+        }
+    }
+
+    impl TryFrom<time::PrimitiveDateTime> for DateTime {
+        type Error = MrXlsxError;
+
+        fn try_from(datetime: time::PrimitiveDateTime) -> Result<Self, Self::Error> {
+            Ok(DateTime::new(
+                datetime.date().try_into()?,
+                datetime.time().into(),
+            ))
+        }
+    }
+
+    impl TryFrom<time::Date> for CellValue {
+        type Error = MrXlsxError;
+
+        fn try_from(date: time::Date) -> Result<Self, MrXlsxError> {
+            Ok(CellValue::Date(date.try_into()?))
+        }
+    }
+
+    impl From<time::Time> for CellValue {
+        fn from(time: time::Time) -> Self {
+            CellValue::Time(time.into())
+        }
+    }
+
+    impl TryFrom<time::PrimitiveDateTime> for CellValue {
+        type Error = MrXlsxError;
+
+        fn try_from(datetime: time::PrimitiveDateTime) -> Result<Self, MrXlsxError> {
+            Ok(CellValue::DateTime(datetime.try_into()?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serial(year: u16, month: u8, day: u8, date_1904: bool) -> f64 {
+        Date::new(year, month, day)
+            .unwrap()
+            .serial(date_1904)
+            .unwrap()
+    }
+
+    #[test]
+    fn date_serials_1900() {
+        assert_eq!(serial(1900, 1, 1, false), 1.0);
+        assert_eq!(serial(1900, 2, 28, false), 59.0);
+        // serial 60 is the 1900-02-29 Excel counts but the calendar does not have
+        assert_eq!(serial(1900, 3, 1, false), 61.0);
+        assert_eq!(serial(2024, 3, 1, false), 45352.0);
+        assert_eq!(serial(9999, 12, 31, false), 2958465.0);
+    }
+
+    #[test]
+    fn date_serials_1904() {
+        assert_eq!(serial(1904, 1, 1, true), 0.0);
+        assert_eq!(serial(2024, 3, 1, true), 45352.0 - 1462.0);
+        assert!(matches!(
+            Date::new(1903, 12, 31).unwrap().serial(true),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_dates_and_times() {
+        assert!(Date::new(2024, 2, 29).is_ok());
+        for (year, month, day) in [(1899, 12, 31), (1900, 2, 29), (2023, 2, 29), (2024, 13, 1)] {
+            assert!(Date::new(year, month, day).is_err(), "{year}-{month}-{day}");
+        }
+        assert!(Time::new(24, 0, 0).is_err());
+        assert!(Time::with_nanos(0, 0, 0, 1_000_000_000).is_err());
+        assert!(Time::with_millis(0, 0, 0, 1000).is_err());
+        assert!(Time::with_millis(0, 0, 0, 5000).is_err());
+        assert!(Time::with_millis(0, 0, 0, u16::MAX).is_err());
+    }
+
+    #[test]
+    fn time_and_datetime_serials() {
+        assert_eq!(Time::new(12, 0, 0).unwrap().serial(), 0.5);
+        assert_eq!(Time::with_millis(0, 0, 43, 200).unwrap().serial(), 0.0005);
+        let datetime = DateTime::new(Date::new(2024, 3, 1).unwrap(), Time::new(18, 0, 0).unwrap());
+        assert_eq!(datetime.serial(false).unwrap(), 45352.75);
+        assert_eq!(datetime.serial(true).unwrap(), 43890.75);
+        assert_eq!(
+            duration_serial(std::time::Duration::from_secs(36 * 3600)),
+            1.5
+        );
+    }
+}
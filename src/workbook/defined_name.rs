@@ -0,0 +1,68 @@
+use crate::workbook::{looks_like_cell_ref, xml_escape};
+
+pub(crate) struct DefinedName {
+    pub(crate) name: String,
+    /// Index of the sheet in the final tab order for sheet-scoped names.
+    pub(crate) local_sheet_id: Option<usize>,
+    pub(crate) hidden: bool,
+    pub(crate) value: String,
+}
+
+impl DefinedName {
+    pub(crate) fn to_xml(&self) -> String {
+        let mut xml = format!("<definedName name=\"{}\"", xml_escape(&self.name));
+        if let Some(id) = self.local_sheet_id {
+            xml.push_str(&format!(" localSheetId=\"{id}\""));
+        }
+        if self.hidden {
+            xml.push_str(" hidden=\"1\"");
+        }
+        xml.push_str(&format!(">{}</definedName>", xml_escape(&self.value)));
+        xml
+    }
+}
+
+/// Defined names and table names start with a letter, `_` or `\`, contain only
+/// letters, digits, `_`, `.` and `\`, and must not read as a cell reference.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    name.chars().count() <= 255
+        && name.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '\\')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '\\')
+        && !looks_like_cell_ref(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        for name in [
+            "Sales", "_total", "\\path", "Tax.Rate", "Tax_2024", "Données",
+        ] {
+            assert!(is_valid_name(name), "{name}");
+        }
+        assert!(is_valid_name(&"x".repeat(255)));
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in [
+            "",
+            "1st",
+            ".x",
+            "has space",
+            "a-b",
+            "A1",
+            "xfd100",
+            "R1C1",
+            "R",
+            "c2",
+        ] {
+            assert!(!is_valid_name(name), "{name}");
+        }
+        assert!(!is_valid_name(&"x".repeat(256)));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::workbook::{
+    image::ObjectAnchor,
+    range::{MAX_COLS, MAX_ROWS},
+    xml_escape,
+};
+
+const EMU_PER_PIXEL: u64 = 9525;
+
+/// Cell position of a drawing object, offsets and sizes in pixels.
+pub(crate) struct Anchor {
+    from: (u32, u32, u32, u32),
+    to: (u32, u32, u32, u32),
+    position: (u64, u64),
+    size: (u32, u32),
+}
+
+impl Anchor {
+    /// Place an object of `width` x `height` pixels at an offset from the top-left
+    /// corner of a 0-based cell, given the pixel size of each column and row.
+    pub(crate) fn new(
+        (row, col): (u32, u32),
+        (x_offset, y_offset): (u32, u32),
+        (width, height): (u32, u32),
+        col_width: impl Fn(u32) -> u32,
+        row_height: impl Fn(u32) -> u32,
+    ) -> Self {
+        let (from_col, from_x, x) = walk(col, x_offset, MAX_COLS, &col_width);
+        let (from_row, from_y, y) = walk(row, y_offset, MAX_ROWS, &row_height);
+        let (to_col, to_x, _) = walk(from_col, from_x.saturating_add(width), MAX_COLS, &col_width);
+        let (to_row, to_y, _) = walk(
+            from_row,
+            from_y.saturating_add(height),
+            MAX_ROWS,
+            &row_height,
+        );
+        Anchor {
+            from: (from_col, from_x, from_row, from_y),
+            to: (to_col, to_x, to_row, to_y),
+            position: (x, y),
+            size: (width, height),
+        }
+    }
+
+    pub(crate) fn to_xml(&self, kind: ObjectAnchor, object_xml: &str) -> String {
+        let from = marker_xml("from", self.from);
+        match kind {
+            ObjectAnchor::OneCell => format!(
+                "<xdr:oneCellAnchor>{from}<xdr:ext cx=\"{}\" cy=\"{}\"/>{object_xml}<xdr:clientData/></xdr:oneCellAnchor>",
+                emu(self.size.0),
+                emu(self.size.1)
+            ),
+            ObjectAnchor::TwoCell => format!(
+                "<xdr:twoCellAnchor>{from}{}{object_xml}<xdr:clientData/></xdr:twoCellAnchor>",
+                marker_xml("to", self.to)
+            ),
+        }
+    }
+
+    /// Column, x offset, row and y offset of both corners, as in a VML `x:Anchor`.
+    pub(crate) fn corners(&self) -> [u32; 8] {
+        let (from, to) = (self.from, self.to);
+        [from.0, from.1, from.2, from.3, to.0, to.1, to.2, to.3]
+    }
+
+    /// Absolute pixel position of the top-left corner.
+    pub(crate) fn position(&self) -> (u64, u64) {
+        self.position
+    }
+
+    /// `<a:xfrm>` with the absolute position and size.
+    pub(crate) fn xfrm_xml(&self, prefix: &str) -> String {
+        format!(
+            "<{prefix}:xfrm><a:off x=\"{}\" y=\"{}\"/><a:ext cx=\"{}\" cy=\"{}\"/></{prefix}:xfrm>",
+            self.position.0 * EMU_PER_PIXEL,
+            self.position.1 * EMU_PER_PIXEL,
+            emu(self.size.0),
+            emu(self.size.1)
+        )
+    }
+}
+
+// move to the cell containing `offset` pixels from the start of `index`, returning
+// that cell, the remaining offset and the absolute pixel position; an object running
+// off the sheet ends at the far edge of its last cell
+fn walk(
+    mut index: u32,
+    mut offset: u32,
+    count: u32,
+    size: &impl Fn(u32) -> u32,
+) -> (u32, u32, u64) {
+    let mut absolute: u64 = (0..index).map(|i| size(i) as u64).sum();
+    while offset >= size(index) && index + 1 < count {
+        offset -= size(index);
+        absolute += size(index) as u64;
+        index += 1;
+    }
+    let offset = offset.min(size(index));
+    (index, offset, absolute + offset as u64)
+}
+
+fn emu(pixels: u32) -> u64 {
+    pixels as u64 * EMU_PER_PIXEL
+}
+
+fn marker_xml(tag: &str, (col, col_off, row, row_off): (u32, u32, u32, u32)) -> String {
+    format!(
+        "<xdr:{tag}><xdr:col>{col}</xdr:col><xdr:colOff>{}</xdr:colOff><xdr:row>{row}</xdr:row><xdr:rowOff>{}</xdr:rowOff></xdr:{tag}>",
+        emu(col_off),
+        emu(row_off)
+    )
+}
+
+/// `<xdr:pic>` of an image related to the drawing as `r_id`.
+pub(crate) fn picture_xml(
+    id: usize,
+    alt_text: Option<&str>,
+    r_id: &str,
+    anchor: &Anchor,
+) -> String {
+    let descr = match alt_text {
+        Some(text) => format!(" descr=\"{}\"", xml_escape(text)),
+        None => String::new(),
+    };
+    format!(
+        concat!(
+            r#"<xdr:pic><xdr:nvPicPr><xdr:cNvPr id="{}" name="Picture {}"{}/>"#,
+            r#"<xdr:cNvPicPr><a:picLocks noChangeAspect="1"/></xdr:cNvPicPr></xdr:nvPicPr>"#,
+            r#"<xdr:blipFill><a:blip r:embed="{}"/><a:stretch><a:fillRect/></a:stretch></xdr:blipFill>"#,
+            r#"<xdr:spPr>{}<a:prstGeom prst="rect"><a:avLst/></a:prstGeom></xdr:spPr></xdr:pic>"#,
+        ),
+        id + 1,
+        id,
+        descr,
+        r_id,
+        anchor.xfrm_xml("a")
+    )
+}
+
+/// `<xdr:graphicFrame>` of a chart related to the drawing as `r_id`.
+pub(crate) fn chart_frame_xml(id: usize, r_id: &str, anchor: Option<&Anchor>) -> String {
+    let xfrm = match anchor {
+        Some(anchor) => anchor.xfrm_xml("xdr"),
+        None => r#"<xdr:xfrm><a:off x="0" y="0"/><a:ext cx="0" cy="0"/></xdr:xfrm>"#.into(),
+    };
+    format!(
+        concat!(
+            r#"<xdr:graphicFrame macro=""><xdr:nvGraphicFramePr><xdr:cNvPr id="{}" name="Chart {}"/>"#,
+            r#"<xdr:cNvGraphicFramePr/></xdr:nvGraphicFramePr>{}"#,
+            r#"<a:graphic><a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/chart">"#,
+            r#"<c:chart xmlns:c="http://schemas.openxmlformats.org/drawingml/2006/chart" r:id="{}"/>"#,
+            r#"</a:graphicData></a:graphic></xdr:graphicFrame>"#,
+        ),
+        id + 1,
+        id,
+        xfrm,
+        r_id
+    )
+}
+
+/// Anchor filling a chartsheet.
+pub(crate) fn absolute_anchor_xml(object_xml: &str) -> String {
+    format!(
+        concat!(
+            r#"<xdr:absoluteAnchor><xdr:pos x="0" y="0"/><xdr:ext cx="8666049" cy="6293304"/>"#,
+            r#"{}<xdr:clientData/></xdr:absoluteAnchor>"#,
+        ),
+        object_xml
+    )
+}
+
+/// `xl/drawings/drawingN.xml` holding the anchored objects of one sheet.
+pub(crate) fn drawing_xml(anchors: &[String]) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<xdr:wsDr xmlns:xdr="http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing" "#,
+            r#"xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            r#"{}</xdr:wsDr>"#,
+        ),
+        anchors.concat()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Anchor;
+    use crate::workbook::range::{MAX_COLS, MAX_ROWS};
+
+    #[test]
+    fn anchor_spans_cells() {
+        let anchor = Anchor::new((1, 1), (10, 5), (150, 40), |_| 64, |_| 20);
+        assert_eq!(anchor.corners(), [1, 10, 1, 5, 3, 32, 3, 5]);
+        assert_eq!(anchor.position(), (74, 25));
+    }
+
+    #[test]
+    fn anchor_stops_at_the_sheet_edge() {
+        let last = (MAX_ROWS - 1, MAX_COLS - 1);
+        let anchor = Anchor::new(last, (u32::MAX, 0), (u32::MAX, u32::MAX), |_| 64, |_| 20);
+        assert_eq!(
+            anchor.corners(),
+            [
+                MAX_COLS - 1,
+                64,
+                MAX_ROWS - 1,
+                0,
+                MAX_COLS - 1,
+                64,
+                MAX_ROWS - 1,
+                20
+            ]
+        );
+        // hidden columns and rows take no space
+        let anchor = Anchor::new((0, 0), (0, 0), (u32::MAX, 10), |_| 0, |_| 0);
+        assert_eq!(
+            anchor.corners(),
+            [
+                MAX_COLS - 1,
+                0,
+                MAX_ROWS - 1,
+                0,
+                MAX_COLS - 1,
+                0,
+                MAX_ROWS - 1,
+                0
+            ]
+        );
+    }
+}
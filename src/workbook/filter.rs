@@ -0,0 +1,105 @@
+use crate::workbook::xml_escape;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOperator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl FilterOperator {
+    fn as_xml_attr(&self) -> Option<&str> {
+        match self {
+            FilterOperator::Equal => None,
+            FilterOperator::NotEqual => Some("notEqual"),
+            FilterOperator::GreaterThan => Some("greaterThan"),
+            FilterOperator::GreaterThanOrEqual => Some("greaterThanOrEqual"),
+            FilterOperator::LessThan => Some("lessThan"),
+            FilterOperator::LessThanOrEqual => Some("lessThanOrEqual"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterCondition {
+    pub operator: FilterOperator,
+    /// Compared value; `*` and `?` act as wildcards for text.
+    pub value: String,
+}
+
+impl FilterCondition {
+    pub fn new<S: Into<String>>(operator: FilterOperator, value: S) -> Self {
+        FilterCondition {
+            operator,
+            value: value.into(),
+        }
+    }
+
+    fn to_xml(&self) -> String {
+        let val = xml_escape(&self.value);
+        match self.operator.as_xml_attr() {
+            Some(op) => format!("<customFilter operator=\"{op}\" val=\"{val}\"/>"),
+            None => format!("<customFilter val=\"{val}\"/>"),
+        }
+    }
+}
+
+/// Criteria pre-set on a filter column. Excel shows the criteria in the dropdown;
+/// rows are not hidden until the filter is reapplied in Excel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterCriteria {
+    /// Keep rows whose value is one of the listed values.
+    Values(Vec<String>),
+    Blanks,
+    NonBlanks,
+    Custom(FilterCondition),
+    And(FilterCondition, FilterCondition),
+    Or(FilterCondition, FilterCondition),
+    Top(u32),
+    Bottom(u32),
+}
+
+impl FilterCriteria {
+    pub fn values<S: Into<String>>(values: Vec<S>) -> Self {
+        FilterCriteria::Values(values.into_iter().map(Into::into).collect())
+    }
+
+    pub(crate) fn to_xml(&self, col_id: u32) -> String {
+        let body = match self {
+            FilterCriteria::Values(values) => {
+                let mut xml = String::from("<filters>");
+                for v in values {
+                    xml.push_str(&format!("<filter val=\"{}\"/>", xml_escape(v)));
+                }
+                xml.push_str("</filters>");
+                xml
+            }
+            FilterCriteria::Blanks => "<filters blank=\"1\"/>".into(),
+            FilterCriteria::NonBlanks => concat!(
+                "<customFilters>",
+                "<customFilter operator=\"notEqual\" val=\" \"/>",
+                "</customFilters>"
+            )
+            .into(),
+            FilterCriteria::Custom(c) => format!("<customFilters>{}</customFilters>", c.to_xml()),
+            FilterCriteria::And(a, b) => format!(
+                "<customFilters and=\"1\">{}{}</customFilters>",
+                a.to_xml(),
+                b.to_xml()
+            ),
+            FilterCriteria::Or(a, b) => {
+                format!(
+                    "<customFilters>{}{}</customFilters>",
+                    a.to_xml(),
+                    b.to_xml()
+                )
+            }
+            FilterCriteria::Top(n) => format!("<top10 val=\"{n}\"/>"),
+            FilterCriteria::Bottom(n) => format!("<top10 top=\"0\" val=\"{n}\"/>"),
+        };
+        format!("<filterColumn colId=\"{col_id}\">{body}</filterColumn>")
+    }
+}
@@ -0,0 +1,567 @@
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{
+        cell::CellError,
+        make_cell_ref,
+        range::{MAX_COLS, MAX_ROWS},
+        xml_escape,
+    },
+};
+
+/// A formula with an optional cached result, shown by readers that do not
+/// recalculate the workbook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    /// Formula text without the leading `=`. Functions added after Excel 2007
+    /// are given their `_xlfn.` prefix when written.
+    pub formula: String,
+    pub result: Option<FormulaResult>,
+    pub kind: FormulaKind,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FormulaKind {
+    /// A single-cell formula, or a dynamic array if it calls a function
+    /// such as `FILTER` or `UNIQUE` that returns an array.
+    #[default]
+    Normal,
+    /// Legacy (Ctrl+Shift+Enter) array formula over `rows` x `cols` cells
+    /// starting at the formula cell.
+    Array { rows: u32, cols: u32 },
+    /// Dynamic array formula whose result spills from the formula cell.
+    Dynamic,
+}
+
+impl Formula {
+    pub fn new<S: Into<String>>(formula: S) -> Self {
+        Formula {
+            formula: formula.into(),
+            result: None,
+            kind: FormulaKind::Normal,
+        }
+    }
+
+    pub fn result<R: Into<FormulaResult>>(mut self, result: R) -> Self {
+        self.result = Some(result.into());
+        self
+    }
+
+    /// Make this a legacy array formula over `rows` x `cols` cells from the
+    /// formula cell. The other cells of the range should be left unwritten.
+    pub fn array(mut self, rows: u32, cols: u32) -> Self {
+        self.kind = FormulaKind::Array {
+            rows: rows.max(1),
+            cols: cols.max(1),
+        };
+        self
+    }
+
+    /// Make this a dynamic array formula, even if it calls no array function.
+    pub fn dynamic(mut self) -> Self {
+        self.kind = FormulaKind::Dynamic;
+        self
+    }
+
+    pub(crate) fn is_dynamic(&self) -> bool {
+        match self.kind {
+            FormulaKind::Normal => function_names(&self.formula)
+                .any(|(_, name)| DYNAMIC_FUNCTIONS.contains(&name.to_ascii_uppercase().as_str())),
+            FormulaKind::Array { .. } => false,
+            FormulaKind::Dynamic => true,
+        }
+    }
+
+    /// Reject an array formula at 0-based `row` and `col` whose cells run past the
+    /// edge of the sheet.
+    pub(crate) fn check_extent(&self, row: u32, col: u32) -> Result<()> {
+        let FormulaKind::Array { rows, cols } = self.kind else {
+            return Ok(());
+        };
+        if row as u64 + rows.max(1) as u64 > MAX_ROWS as u64
+            || col as u64 + cols.max(1) as u64 > MAX_COLS as u64
+        {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "array formula of {rows}x{cols} cells at {} runs past the edge of the sheet",
+                make_cell_ref(row + 1, col)
+            )));
+        }
+        Ok(())
+    }
+
+    /// `c` attributes and `<f>`/`<v>` content of the cell at 1-based `row`
+    /// and 0-based `col`.
+    pub(crate) fn to_xml(&self, row: u32, col: u32) -> (String, String) {
+        let text = xml_escape(&with_future_prefixes(&self.formula));
+        let (cm, f) = match self.kind {
+            FormulaKind::Array { rows, cols } => {
+                let range = format!(
+                    "{}:{}",
+                    make_cell_ref(row, col),
+                    make_cell_ref(row + rows.max(1) - 1, col + cols.max(1) - 1)
+                );
+                ("", format!("<f t=\"array\" ref=\"{range}\">{text}</f>"))
+            }
+            _ if self.is_dynamic() => {
+                let cell = make_cell_ref(row, col);
+                (
+                    " cm=\"1\"",
+                    format!("<f t=\"array\" ref=\"{cell}\">{text}</f>"),
+                )
+            }
+            _ => ("", format!("<f>{text}</f>")),
+        };
+        let (t, content) = match &self.result {
+            None => ("", format!("{f}<v/>")),
+            Some(FormulaResult::Number(n)) => ("", format!("{f}<v>{n}</v>")),
+            Some(FormulaResult::Text(text)) => {
+                (" t=\"str\"", format!("{f}<v>{}</v>", xml_escape(text)))
+            }
+            Some(FormulaResult::Bool(b)) => (" t=\"b\"", format!("{f}<v>{}</v>", *b as u8)),
+            Some(FormulaResult::Error(e)) => (" t=\"e\"", format!("{f}<v>{}</v>", e.as_str())),
+        };
+        (format!("{t}{cm}"), content)
+    }
+}
+
+impl From<&str> for Formula {
+    fn from(v: &str) -> Self {
+        Formula::new(v)
+    }
+}
+
+impl From<String> for Formula {
+    fn from(v: String) -> Self {
+        Formula::new(v)
+    }
+}
+
+// functions returning arrays, which make a formula spill
+const DYNAMIC_FUNCTIONS: &[&str] = &[
+    "ANCHORARRAY",
+    "CHOOSECOLS",
+    "CHOOSEROWS",
+    "DROP",
+    "EXPAND",
+    "FILTER",
+    "HSTACK",
+    "MAKEARRAY",
+    "RANDARRAY",
+    "SEQUENCE",
+    "SORT",
+    "SORTBY",
+    "TAKE",
+    "TEXTSPLIT",
+    "TOCOL",
+    "TOROW",
+    "UNIQUE",
+    "VSTACK",
+    "WRAPCOLS",
+    "WRAPROWS",
+];
+
+// functions stored with the `_xlfn._xlws.` prefix
+const WORKSHEET_FUNCTIONS: &[&str] = &["FILTER", "SORT"];
+
+// functions added after Excel 2007, stored with the `_xlfn.` prefix
+const FUTURE_FUNCTIONS: &[&str] = &[
+    "ACOT",
+    "ACOTH",
+    "AGGREGATE",
+    "ANCHORARRAY",
+    "ARABIC",
+    "ARRAYTOTEXT",
+    "BASE",
+    "BETA.DIST",
+    "BETA.INV",
+    "BINOM.DIST",
+    "BINOM.DIST.RANGE",
+    "BINOM.INV",
+    "BITAND",
+    "BITLSHIFT",
+    "BITOR",
+    "BITRSHIFT",
+    "BITXOR",
+    "BYCOL",
+    "BYROW",
+    "CEILING.MATH",
+    "CEILING.PRECISE",
+    "CHISQ.DIST",
+    "CHISQ.DIST.RT",
+    "CHISQ.INV",
+    "CHISQ.INV.RT",
+    "CHISQ.TEST",
+    "CHOOSECOLS",
+    "CHOOSEROWS",
+    "COMBINA",
+    "CONCAT",
+    "CONFIDENCE.NORM",
+    "CONFIDENCE.T",
+    "COT",
+    "COTH",
+    "COVARIANCE.P",
+    "COVARIANCE.S",
+    "CSC",
+    "CSCH",
+    "DAYS",
+    "DECIMAL",
+    "DROP",
+    "ERF.PRECISE",
+    "ERFC.PRECISE",
+    "EXPAND",
+    "EXPON.DIST",
+    "F.DIST",
+    "F.DIST.RT",
+    "F.INV",
+    "F.INV.RT",
+    "F.TEST",
+    "FILTERXML",
+    "FLOOR.MATH",
+    "FLOOR.PRECISE",
+    "FORECAST.ETS",
+    "FORECAST.ETS.CONFINT",
+    "FORECAST.ETS.SEASONALITY",
+    "FORECAST.ETS.STAT",
+    "FORECAST.LINEAR",
+    "FORMULATEXT",
+    "GAMMA",
+    "GAMMA.DIST",
+    "GAMMA.INV",
+    "GAMMALN.PRECISE",
+    "GAUSS",
+    "HSTACK",
+    "HYPGEOM.DIST",
+    "IFNA",
+    "IFS",
+    "IMAGE",
+    "IMCOSH",
+    "IMCOT",
+    "IMCSC",
+    "IMCSCH",
+    "IMSEC",
+    "IMSECH",
+    "IMSINH",
+    "IMTAN",
+    "ISFORMULA",
+    "ISOMITTED",
+    "ISOWEEKNUM",
+    "LAMBDA",
+    "LET",
+    "LOGNORM.DIST",
+    "LOGNORM.INV",
+    "MAKEARRAY",
+    "MAP",
+    "MAXIFS",
+    "MINIFS",
+    "MODE.MULT",
+    "MODE.SNGL",
+    "MUNIT",
+    "NEGBINOM.DIST",
+    "NETWORKDAYS.INTL",
+    "NORM.DIST",
+    "NORM.INV",
+    "NORM.S.DIST",
+    "NORM.S.INV",
+    "NUMBERVALUE",
+    "PDURATION",
+    "PERCENTILE.EXC",
+    "PERCENTILE.INC",
+    "PERCENTRANK.EXC",
+    "PERCENTRANK.INC",
+    "PERMUTATIONA",
+    "PHI",
+    "POISSON.DIST",
+    "QUARTILE.EXC",
+    "QUARTILE.INC",
+    "QUERYSTRING",
+    "RANDARRAY",
+    "RANK.AVG",
+    "RANK.EQ",
+    "REDUCE",
+    "RRI",
+    "SCAN",
+    "SEC",
+    "SECH",
+    "SEQUENCE",
+    "SHEET",
+    "SHEETS",
+    "SINGLE",
+    "SKEW.P",
+    "SORTBY",
+    "STDEV.P",
+    "STDEV.S",
+    "SWITCH",
+    "T.DIST",
+    "T.DIST.2T",
+    "T.DIST.RT",
+    "T.INV",
+    "T.INV.2T",
+    "T.TEST",
+    "TAKE",
+    "TEXTAFTER",
+    "TEXTBEFORE",
+    "TEXTJOIN",
+    "TEXTSPLIT",
+    "TOCOL",
+    "TOROW",
+    "UNICHAR",
+    "UNICODE",
+    "UNIQUE",
+    "VALUETOTEXT",
+    "VAR.P",
+    "VAR.S",
+    "VSTACK",
+    "WEBSERVICE",
+    "WEIBULL.DIST",
+    "WORKDAY.INTL",
+    "WRAPCOLS",
+    "WRAPROWS",
+    "XLOOKUP",
+    "XMATCH",
+    "XOR",
+    "Z.TEST",
+];
+
+/// Byte offset and name of each function call in `formula`, skipping string
+/// literals and quoted sheet names.
+fn function_names(formula: &str) -> impl Iterator<Item = (usize, &str)> {
+    let bytes = formula.as_bytes();
+    let mut names = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                // a doubled quote is an escaped quote inside the literal
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == quote && bytes.get(i + 1) != Some(&quote) {
+                        break;
+                    }
+                    i += if bytes[i] == quote { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'.' | b'_'))
+                {
+                    i += 1;
+                }
+                if bytes.get(i) == Some(&b'(') {
+                    names.push((start, &formula[start..i]));
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    names.into_iter()
+}
+
+/// `formula` with the storage prefix added to functions newer than Excel 2007.
+fn with_future_prefixes(formula: &str) -> String {
+    let mut out = String::with_capacity(formula.len());
+    let mut last = 0;
+    for (start, name) in function_names(formula) {
+        let upper = name.to_ascii_uppercase();
+        let prefix = if WORKSHEET_FUNCTIONS.contains(&upper.as_str()) {
+            "_xlfn._xlws."
+        } else if FUTURE_FUNCTIONS.contains(&upper.as_str()) {
+            "_xlfn."
+        } else {
+            continue;
+        };
+        out.push_str(&formula[last..start]);
+        out.push_str(prefix);
+        out.push_str(&upper);
+        last = start + name.len();
+    }
+    out.push_str(&formula[last..]);
+    out
+}
+
+/// Cached value of a formula cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaResult {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(CellError),
+}
+
+impl From<f64> for FormulaResult {
+    fn from(v: f64) -> Self {
+        FormulaResult::Number(v)
+    }
+}
+
+impl From<&str> for FormulaResult {
+    fn from(v: &str) -> Self {
+        FormulaResult::Text(v.to_string())
+    }
+}
+
+impl From<String> for FormulaResult {
+    fn from(v: String) -> Self {
+        FormulaResult::Text(v)
+    }
+}
+
+impl From<bool> for FormulaResult {
+    fn from(v: bool) -> Self {
+        FormulaResult::Bool(v)
+    }
+}
+
+impl From<CellError> for FormulaResult {
+    fn from(v: CellError) -> Self {
+        FormulaResult::Error(v)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CalcMode {
+    #[default]
+    Auto,
+    /// Automatic, except data tables.
+    AutoNoTable,
+    Manual,
+}
+
+/// Workbook calculation settings written to `calcPr`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcOptions {
+    pub mode: CalcMode,
+    /// Recalculate every formula when the workbook is opened.
+    pub full_calc_on_load: bool,
+    /// Iteration limit and maximum change for circular references, if allowed.
+    pub iterate: Option<(u32, f64)>,
+}
+
+impl Default for CalcOptions {
+    fn default() -> Self {
+        CalcOptions {
+            mode: CalcMode::Auto,
+            full_calc_on_load: true,
+            iterate: None,
+        }
+    }
+}
+
+impl CalcOptions {
+    pub fn new() -> Self {
+        CalcOptions::default()
+    }
+
+    pub fn mode(mut self, mode: CalcMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    pub fn full_calc_on_load(mut self, enabled: bool) -> Self {
+        self.full_calc_on_load = enabled;
+        self
+    }
+    pub fn iterate(mut self, count: u32, max_change: f64) -> Self {
+        self.iterate = Some((count, max_change));
+        self
+    }
+
+    pub(crate) fn to_xml(&self) -> String {
+        let mut xml = String::from("<calcPr");
+        match self.mode {
+            CalcMode::Auto => {}
+            CalcMode::AutoNoTable => xml.push_str(" calcMode=\"autoNoTable\""),
+            CalcMode::Manual => xml.push_str(" calcMode=\"manual\""),
+        }
+        if self.full_calc_on_load {
+            xml.push_str(" fullCalcOnLoad=\"1\"");
+        }
+        if let Some((count, delta)) = self.iterate {
+            xml.push_str(&format!(
+                " iterate=\"1\" iterateCount=\"{count}\" iterateDelta=\"{delta}\""
+            ));
+        }
+        xml.push_str("/>");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workbook::cell::CellValue;
+
+    #[test]
+    fn future_functions_are_prefixed() {
+        assert_eq!(with_future_prefixes("CONCAT(A1,B1)"), "_xlfn.CONCAT(A1,B1)");
+        assert_eq!(with_future_prefixes("concat(a1)"), "_xlfn.CONCAT(a1)");
+        assert_eq!(
+            with_future_prefixes("FILTER(A1:A9,B1:B9>0)"),
+            "_xlfn._xlws.FILTER(A1:A9,B1:B9>0)"
+        );
+        assert_eq!(
+            with_future_prefixes("IFS(A1>0,XLOOKUP(1,B:B,C:C),TRUE,0)"),
+            "_xlfn.IFS(A1>0,_xlfn.XLOOKUP(1,B:B,C:C),TRUE,0)"
+        );
+        for unchanged in [
+            "SUM(A1:A3)",
+            "_xlfn.CONCAT(A1)",
+            "\"CONCAT(\"\"x\"\")\"&A1",
+            "'CONCAT(x'!A1",
+            "LEN(Sheet1!A1)",
+        ] {
+            assert_eq!(with_future_prefixes(unchanged), unchanged);
+        }
+    }
+
+    #[test]
+    fn dynamic_formulas() {
+        assert!(!Formula::new("SUM(A1:A3)").is_dynamic());
+        assert!(Formula::new("unique(A1:A9)").is_dynamic());
+        assert!(Formula::new("SUM(SEQUENCE(3))").is_dynamic());
+        assert!(!Formula::new("\"FILTER(\"&A1").is_dynamic());
+        assert!(Formula::new("A1:A3*2").dynamic().is_dynamic());
+        assert!(!Formula::new("UNIQUE(A1:A9)").array(2, 1).is_dynamic());
+    }
+
+    #[test]
+    fn formula_xml() {
+        let (attrs, xml) = Formula::new("UNIQUE(A1:A9)").to_xml(1, 1);
+        assert_eq!(attrs, " cm=\"1\"");
+        assert_eq!(xml, "<f t=\"array\" ref=\"B1\">_xlfn.UNIQUE(A1:A9)</f><v/>");
+        let (attrs, xml) = Formula::new("A1:A2*B1:B2")
+            .array(2, 1)
+            .result(3.0)
+            .to_xml(1, 2);
+        assert_eq!(attrs, "");
+        assert_eq!(xml, "<f t=\"array\" ref=\"C1:C2\">A1:A2*B1:B2</f><v>3</v>");
+        let (attrs, xml) = Formula::new("A1&\"x\"").result("ax").to_xml(2, 0);
+        assert_eq!(attrs, " t=\"str\"");
+        assert_eq!(xml, "<f>A1&amp;&quot;x&quot;</f><v>ax</v>");
+    }
+
+    #[test]
+    fn array_extent_must_fit_the_sheet() {
+        let array = |rows, cols| Formula::new("A1:B2").array(rows, cols);
+        assert!(array(2, 2).check_extent(MAX_ROWS - 2, MAX_COLS - 2).is_ok());
+        assert!(matches!(
+            array(2, 1).check_extent(MAX_ROWS - 1, 0),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            array(1, u32::MAX).check_extent(0, 1),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        assert!(Formula::new("A1").check_extent(u32::MAX, u32::MAX).is_ok());
+    }
+
+    #[test]
+    fn formula_from_text() {
+        let formula: Formula = "SUM(A1:A3)".into();
+        assert_eq!(formula, Formula::new("SUM(A1:A3)"));
+        assert_eq!(
+            CellValue::Formula(String::from("A1*2").into()),
+            CellValue::formula("A1*2")
+        );
+    }
+}
@@ -0,0 +1,78 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum HyperlinkTarget {
+    Url(String),
+    Email(String),
+    /// A location in the workbook, e.g. `'Summary'!A1` or a defined name.
+    Internal(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hyperlink {
+    pub target: HyperlinkTarget,
+    pub text: Option<String>,
+    pub tooltip: Option<String>,
+}
+
+impl Hyperlink {
+    pub fn url<S: Into<String>>(url: S) -> Self {
+        Hyperlink::new(HyperlinkTarget::Url(url.into()))
+    }
+    pub fn mailto<S: Into<String>>(address: S) -> Self {
+        Hyperlink::new(HyperlinkTarget::Email(address.into()))
+    }
+    pub fn internal<S: Into<String>>(location: S) -> Self {
+        Hyperlink::new(HyperlinkTarget::Internal(location.into()))
+    }
+
+    fn new(target: HyperlinkTarget) -> Self {
+        Hyperlink {
+            target,
+            text: None,
+            tooltip: None,
+        }
+    }
+
+    /// Text shown in the cell, defaults to the link target.
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+    pub fn tooltip<S: Into<String>>(mut self, tooltip: S) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    pub(crate) fn display_text(&self) -> &str {
+        if let Some(text) = &self.text {
+            return text;
+        }
+        match &self.target {
+            HyperlinkTarget::Url(s) | HyperlinkTarget::Email(s) | HyperlinkTarget::Internal(s) => s,
+        }
+    }
+
+    /// Relationship target for external links, without any `#fragment`.
+    pub(crate) fn external_target(&self) -> Option<String> {
+        match &self.target {
+            HyperlinkTarget::Url(url) => Some(
+                url.split_once('#')
+                    .map_or(url.as_str(), |(u, _)| u)
+                    .to_string(),
+            ),
+            HyperlinkTarget::Email(address) if address.starts_with("mailto:") => {
+                Some(address.clone())
+            }
+            HyperlinkTarget::Email(address) => Some(format!("mailto:{address}")),
+            HyperlinkTarget::Internal(_) => None,
+        }
+    }
+
+    /// In-document location: the whole target for internal links, the fragment for URLs.
+    pub(crate) fn location(&self) -> Option<&str> {
+        match &self.target {
+            HyperlinkTarget::Url(url) => url.split_once('#').map(|(_, f)| f),
+            HyperlinkTarget::Email(_) => None,
+            HyperlinkTarget::Internal(location) => Some(location),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::path::Path;
+
+use crate::{Result, error::MrXlsxError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+}
+
+impl ImageFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Gif => "gif",
+        }
+    }
+
+    pub(crate) fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+}
+
+/// How a drawing follows the cells under it when rows and columns are resized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObjectAnchor {
+    /// Move with the top-left cell, keep its size.
+    #[default]
+    OneCell,
+    /// Move and size with the cells under it.
+    TwoCell,
+}
+
+/// A PNG, JPEG or GIF image placed on a sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub(crate) data: Vec<u8>,
+    pub(crate) format: ImageFormat,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) dpi: (f64, f64),
+    /// Offset from the top-left corner of the anchor cell, in pixels.
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub x_scale: f64,
+    pub y_scale: f64,
+    pub alt_text: Option<String>,
+    pub anchor: ObjectAnchor,
+}
+
+impl Image {
+    /// Image from the bytes of a PNG, JPEG or GIF file. The format and pixel size
+    /// are read from the file header.
+    pub fn new(data: Vec<u8>) -> Result<Self> {
+        let (format, width, height, dpi) = sniff(&data).ok_or_else(|| {
+            MrXlsxError::InvalidArgument("image is not a readable PNG, JPEG or GIF".into())
+        })?;
+        Ok(Image {
+            data,
+            format,
+            width,
+            height,
+            dpi,
+            x_offset: 0,
+            y_offset: 0,
+            x_scale: 1.0,
+            y_scale: 1.0,
+            alt_text: None,
+            anchor: ObjectAnchor::default(),
+        })
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Image::new(std::fs::read(path)?)
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+    /// Size in pixels as stored in the file.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn offset(mut self, x: u32, y: u32) -> Self {
+        self.x_offset = x;
+        self.y_offset = y;
+        self
+    }
+    pub fn scale(mut self, x: f64, y: f64) -> Self {
+        self.x_scale = x.max(0.0);
+        self.y_scale = y.max(0.0);
+        self
+    }
+    pub fn alt_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.alt_text = Some(text.into());
+        self
+    }
+    pub fn anchor(mut self, anchor: ObjectAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Displayed size in pixels, after resolution and scaling. Sizes too large for
+    /// a `u32` are capped, and a NaN scale gives an empty image.
+    pub(crate) fn display_size(&self) -> (u32, u32) {
+        let width = self.width as f64 * 96.0 / self.dpi.0 * self.x_scale;
+        let height = self.height as f64 * 96.0 / self.dpi.1 * self.y_scale;
+        (pixels(width), pixels(height))
+    }
+}
+
+fn pixels(size: f64) -> u32 {
+    if size.is_nan() {
+        0
+    } else {
+        size.round().clamp(0.0, u32::MAX as f64) as u32
+    }
+}
+
+type ImageInfo = (ImageFormat, u32, u32, (f64, f64));
+
+fn sniff(data: &[u8]) -> Option<ImageInfo> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        sniff_png(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        sniff_jpeg(data)
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        let width = u16::from_le_bytes(data.get(6..8)?.try_into().ok()?);
+        let height = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?);
+        Some((ImageFormat::Gif, width as u32, height as u32, (96.0, 96.0)))
+    } else {
+        None
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn sniff_png(data: &[u8]) -> Option<ImageInfo> {
+    // IHDR is always the first chunk
+    let width = be_u32(data, 16)?;
+    let height = be_u32(data, 20)?;
+    let mut dpi = (96.0, 96.0);
+
+    let mut at = 8;
+    while let (Some(len), Some(kind)) = (be_u32(data, at), data.get(at + 4..at + 8)) {
+        match kind {
+            b"pHYs" if data.get(at + 16) == Some(&1) => {
+                // pixels per metre
+                let x = be_u32(data, at + 8)? as f64 * 0.0254;
+                let y = be_u32(data, at + 12)? as f64 * 0.0254;
+                if x > 0.0 && y > 0.0 {
+                    dpi = (x, y);
+                }
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + len as usize;
+    }
+    Some((ImageFormat::Png, width, height, dpi))
+}
+
+fn sniff_jpeg(data: &[u8]) -> Option<ImageInfo> {
+    let mut dpi = (96.0, 96.0);
+    let mut at = 2;
+    loop {
+        if *data.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        let len = be_u16(data, at + 2)? as usize;
+        match marker {
+            0xE0 if data.get(at + 4..at + 9) == Some(b"JFIF\0") => {
+                let units = *data.get(at + 11)?;
+                let x = be_u16(data, at + 12)? as f64;
+                let y = be_u16(data, at + 14)? as f64;
+                if x > 0.0 && y > 0.0 {
+                    match units {
+                        1 => dpi = (x, y),
+                        2 => dpi = (x * 2.54, y * 2.54),
+                        _ => {}
+                    }
+                }
+            }
+            // start of frame, excluding DHT, JPG and DAC markers
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = be_u16(data, at + 5)? as u32;
+                let width = be_u16(data, at + 7)? as u32;
+                return Some((ImageFormat::Jpeg, width, height, dpi));
+            }
+            _ => {}
+        }
+        at += 2 + len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = (body.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out.extend_from_slice(&[0; 4]); // crc, not checked
+        out
+    }
+
+    fn png(width: u32, height: u32, pixels_per_metre: Option<u32>) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut ihdr = [width.to_be_bytes(), height.to_be_bytes()].concat();
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        data.extend(chunk(b"IHDR", &ihdr));
+        if let Some(ppm) = pixels_per_metre {
+            let mut phys = [ppm.to_be_bytes(), ppm.to_be_bytes()].concat();
+            phys.push(1);
+            data.extend(chunk(b"pHYs", &phys));
+        }
+        data.extend(chunk(b"IEND", &[]));
+        data
+    }
+
+    #[test]
+    fn sniff_png() {
+        assert_eq!(
+            sniff(&png(640, 480, None)),
+            Some((ImageFormat::Png, 640, 480, (96.0, 96.0)))
+        );
+        let (_, _, _, (x, y)) = sniff(&png(10, 20, Some(5906))).unwrap();
+        assert_eq!((x.round(), y.round()), (150.0, 150.0));
+        assert_eq!(sniff(&png(1, 1, None)[..20]), None);
+    }
+
+    #[test]
+    fn sniff_jpeg() {
+        let mut data = vec![0xFF, 0xD8];
+        // JFIF header at 72 dpi
+        data.extend([0xFF, 0xE0, 0, 16]);
+        data.extend(b"JFIF\0");
+        data.extend([1, 1, 1, 0, 72, 0, 72, 0, 0]);
+        // a Huffman table, which shares the start of frame marker range
+        data.extend([0xFF, 0xC4, 0, 3, 0]);
+        // baseline start of frame: precision, height, width
+        data.extend([0xFF, 0xC0, 0, 11, 8, 0x01, 0x2C, 0x01, 0x90, 3, 0, 0, 0]);
+        assert_eq!(
+            sniff(&data),
+            Some((ImageFormat::Jpeg, 400, 300, (72.0, 72.0)))
+        );
+        assert_eq!(sniff(&data[..12]), None);
+    }
+
+    #[test]
+    fn sniff_gif() {
+        let mut data = b"GIF89a".to_vec();
+        data.extend([0x20, 0x03, 0x58, 0x02]);
+        assert_eq!(
+            sniff(&data),
+            Some((ImageFormat::Gif, 800, 600, (96.0, 96.0)))
+        );
+        assert_eq!(sniff(b"GIF89a\x20"), None);
+    }
+
+    #[test]
+    fn sniff_rejects_other_data() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"BM\0\0"), None);
+        assert!(matches!(
+            Image::new(b"not an image".to_vec()),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn display_size_is_capped() {
+        // one pixel per metre, well under one dpi
+        let image = Image::new(png(u32::MAX, 10, Some(1))).unwrap();
+        assert_eq!(image.display_size().0, u32::MAX);
+        let image = Image::new(png(10, 10, None)).unwrap().scale(f64::MAX, 2.0);
+        assert_eq!(image.display_size(), (u32::MAX, 20));
+        let mut image = Image::new(png(10, 10, None)).unwrap();
+        image.x_scale = f64::NAN;
+        assert_eq!(image.display_size(), (0, 10));
+    }
+}
@@ -1,8 +1,9 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{Seek, SeekFrom, Write},
+    io::{Cursor, Seek, Write},
     sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
 use zip::{ZipWriter, write::SimpleFileOptions};
@@ -10,21 +11,63 @@ use zip::{ZipWriter, write::SimpleFileOptions};
 use crate::{
     Result,
     error::MrXlsxError,
-    workbook::{cell::CellValue, sheet::SheetWriter, style::StyleRegistry},
+    workbook::{
+        cell::{CellValue, NonFinitePolicy},
+        chart::Chart,
+        date::duration_serial,
+        defined_name::{DefinedName, is_valid_name},
+        drawing::{absolute_anchor_xml, chart_frame_xml, drawing_xml},
+        formula::CalcOptions,
+        note::comments_xml,
+        properties::DocProperties,
+        range::CellRange,
+        rels::{
+            REL_CHART, REL_CHARTSHEET, REL_CORE_PROPERTIES, REL_CUSTOM_PROPERTIES, REL_DRAWING,
+            REL_EXTENDED_PROPERTIES, REL_OFFICE_DOCUMENT, REL_WORKSHEET, Relationships,
+        },
+        rich_text::runs_xml,
+        shared_strings::{SharedStringTable, text_xml},
+        sheet::SheetWriter,
+        style::StyleRegistry,
+        table::TableRegistry,
+    },
 };
 pub mod builder;
 pub mod cell;
+pub mod chart;
+pub mod column;
+pub mod conditional;
+pub mod date;
+mod defined_name;
+mod drawing;
+pub mod filter;
+pub mod formula;
+pub mod hyperlink;
+pub mod image;
+pub mod note;
+pub mod properties;
+mod range;
+#[cfg(feature = "serde")]
+mod record;
+mod rels;
+pub mod rich_text;
+pub mod row;
+mod shared_strings;
 pub mod sheet;
 pub mod style;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod validation;
+mod view;
 
-const RELS_DOT_RELS: &str = concat!(
-    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
-    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
-    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
-    r#"</Relationships>"#,
-);
-
-fn workbook_xml(order: &[String]) -> String {
+fn workbook_xml(
+    order: &[String],
+    active_tab: usize,
+    defined_names: &[DefinedName],
+    date_1904: bool,
+    calc: &CalcOptions,
+) -> String {
     let mut sheets = String::new();
     for (i, name) in order.iter().enumerate() {
         let sheet_id = i + 1;
@@ -35,26 +78,47 @@ fn workbook_xml(order: &[String]) -> String {
         ));
     }
 
+    let mut names = String::new();
+    if !defined_names.is_empty() {
+        names.push_str("<definedNames>");
+        for name in defined_names {
+            names.push_str(&name.to_xml());
+        }
+        names.push_str("</definedNames>");
+    }
+
     format!(
         concat!(
             r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
             r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
             r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
-            r#"<bookViews><workbookView activeTab="0"/></bookViews>"#,
+            r#"{}<bookViews><workbookView activeTab="{}"/></bookViews>"#,
             r#"<sheets>{}</sheets>"#,
-            r#"<calcPr fullCalcOnLoad="1"/>"#,
+            r#"{}"#,
+            r#"{}"#,
             r#"</workbook>"#,
         ),
-        sheets
+        if date_1904 {
+            r#"<workbookPr date1904="1"/>"#
+        } else {
+            ""
+        },
+        active_tab,
+        sheets,
+        names,
+        calc.to_xml()
     )
 }
 
-fn workbook_rels_xml(sheet_count: usize) -> String {
+/// `sheets` holds the relationship type and target of each tab in order.
+fn workbook_rels_xml(sheets: &[(&str, String)], shared_strings: bool, metadata: bool) -> String {
     let mut rels = String::new();
+    let sheet_count = sheets.len();
 
-    for i in 1..=sheet_count {
+    for (i, (rel_type, target)) in sheets.iter().enumerate() {
         rels.push_str(&format!(
-            r#"<Relationship Id="rId{i}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{i}.xml"/>"#
+            r#"<Relationship Id="rId{}" Type="{rel_type}" Target="{target}"/>"#,
+            i + 1
         ));
     }
 
@@ -63,6 +127,20 @@ fn workbook_rels_xml(sheet_count: usize) -> String {
         r#"<Relationship Id="rId{styles_id}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#
     ));
 
+    if shared_strings {
+        let sst_id = sheet_count + 2;
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{sst_id}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings" Target="sharedStrings.xml"/>"#
+        ));
+    }
+
+    if metadata {
+        let metadata_id = sheet_count + 2 + shared_strings as usize;
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{metadata_id}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/sheetMetadata" Target="metadata.xml"/>"#
+        ));
+    }
+
     format!(
         concat!(
             r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
@@ -74,12 +152,54 @@ fn workbook_rels_xml(sheet_count: usize) -> String {
     )
 }
 
-fn content_types_xml(sheet_count: usize) -> String {
-    let mut overrides = String::new();
+const CT_WORKSHEET: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+const CT_SHARED_STRINGS: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml";
+const CT_CHARTSHEET: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.chartsheet+xml";
+const CT_CHART: &str = "application/vnd.openxmlformats-officedocument.drawingml.chart+xml";
+const CT_TABLE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.table+xml";
+const CT_COMMENTS: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.comments+xml";
+const CT_DRAWING: &str = "application/vnd.openxmlformats-officedocument.drawing+xml";
+const CT_VML: &str = "application/vnd.openxmlformats-officedocument.vmlDrawing";
+const CT_SHEET_METADATA: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheetMetadata+xml";
+const CT_CORE_PROPERTIES: &str = "application/vnd.openxmlformats-package.core-properties+xml";
+const CT_EXTENDED_PROPERTIES: &str =
+    "application/vnd.openxmlformats-officedocument.extended-properties+xml";
+const CT_CUSTOM_PROPERTIES: &str =
+    "application/vnd.openxmlformats-officedocument.custom-properties+xml";
+
+/// Cell metadata marking the cells written with `cm="1"` as dynamic arrays.
+const METADATA_XML: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<metadata xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+    r#"xmlns:xda="http://schemas.microsoft.com/office/spreadsheetml/2017/dynamicarray">"#,
+    r#"<metadataTypes count="1"><metadataType name="XLDAPR" minSupportedVersion="120000" copy="1" "#,
+    r#"pasteAll="1" pasteValues="1" merge="1" splitFirst="1" rowColShift="1" clearFormats="1" "#,
+    r#"clearComments="1" assign="1" coerce="1" cellMeta="1"/></metadataTypes>"#,
+    r#"<futureMetadata name="XLDAPR" count="1"><bk><extLst><ext uri="{bdbb8cdc-fa1e-496e-a857-3c3f30c029c3}">"#,
+    r#"<xda:dynamicArrayProperties fDynamic="1" fCollapsed="0"/></ext></extLst></bk></futureMetadata>"#,
+    r#"<cellMetadata count="1"><bk><rc t="1" v="0"/></bk></cellMetadata>"#,
+    r#"</metadata>"#,
+);
+
+/// `defaults` map file extensions and `overrides` map part names to content types,
+/// on top of the parts every package has.
+fn content_types_xml(defaults: &[(&str, &str)], overrides: &[(String, &str)]) -> String {
+    let mut default_types = String::new();
+    for (extension, content_type) in defaults {
+        default_types.push_str(&format!(
+            r#"<Default Extension="{extension}" ContentType="{content_type}"/>"#
+        ));
+    }
 
-    for i in 1..=sheet_count {
-        overrides.push_str(&format!(
-            r#"<Override PartName="/xl/worksheets/sheet{i}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+    let mut override_types = String::new();
+    for (part_name, content_type) in overrides {
+        override_types.push_str(&format!(
+            r#"<Override PartName="{part_name}" ContentType="{content_type}"/>"#
         ));
     }
 
@@ -89,30 +209,100 @@ fn content_types_xml(sheet_count: usize) -> String {
             r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
             r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
             r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+            r#"{}"#,
             r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
             r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
             r#"{}"#,
             r#"</Types>"#,
         ),
-        overrides
+        default_types, override_types
     )
 }
 
+/// Numbers of the package parts belonging to one sheet, e.g. `xl/tables/table3.xml`.
+#[derive(Default)]
+pub(crate) struct PartIds {
+    pub(crate) first_table: usize,
+    pub(crate) comments: Option<usize>,
+    /// First block of VML shape ids for the notes, see `note::vml_xml`.
+    pub(crate) vml_block: usize,
+    pub(crate) drawing: Option<usize>,
+    pub(crate) first_chart: usize,
+    /// Relative targets of the drawing's images, e.g. `../media/image1.png`.
+    pub(crate) media: Vec<String>,
+}
+
+fn chartsheet_xml(tab_selected: bool) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<chartsheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+            r#"<sheetViews><sheetView{} zoomToFit="1" workbookViewId="0"/></sheetViews>"#,
+            r#"<pageMargins left="0.7" right="0.7" top="0.75" bottom="0.75" header="0.3" footer="0.3"/>"#,
+            r#"<drawing r:id="rId1"/></chartsheet>"#,
+        ),
+        if tab_selected {
+            r#" tabSelected="1""#
+        } else {
+            ""
+        }
+    )
+}
+
+#[derive(Default)]
+pub(crate) struct WorkbookOptions {
+    pub(crate) shared_strings: Option<usize>,
+    pub(crate) properties: DocProperties,
+    pub(crate) date_1904: bool,
+    pub(crate) non_finite: NonFinitePolicy,
+    pub(crate) calc: CalcOptions,
+}
+
 pub struct Workbook {
-    output_path: String,
+    // None for workbooks only written with `finish_into`
+    output_path: Option<String>,
     sheets: HashMap<String, SheetWriter>,
+    // sheets moved out with `detach_sheet`, keeping their name and tab position
+    detached: HashSet<String>,
+    chartsheets: HashMap<String, Chart>,
     insertion_order: Vec<String>,
     style_reg: Arc<Mutex<StyleRegistry>>,
+    shared_strings: Option<Arc<Mutex<SharedStringTable>>>,
+    tables: Arc<Mutex<TableRegistry>>,
+    active_sheet: Option<String>,
+    // user names with the sheet they are scoped to, resolved to an index at finish
+    defined_names: Vec<(Option<String>, DefinedName)>,
+    properties: DocProperties,
+    date_1904: bool,
+    non_finite: NonFinitePolicy,
+    calc: CalcOptions,
 }
 
 impl Workbook {
-    pub(crate) fn new_with_builder(path: String, sheets: Vec<String>) -> Result<Self> {
+    pub(crate) fn new_with_builder(
+        path: Option<String>,
+        sheets: Vec<String>,
+        options: WorkbookOptions,
+    ) -> Result<Self> {
         let mut insertion_order = vec![];
         let mut _sheets = HashMap::new();
         let style_reg = Arc::new(Mutex::new(StyleRegistry::new()));
+        let shared_strings = match options.shared_strings {
+            Some(capacity) => Some(Arc::new(Mutex::new(SharedStringTable::new(capacity)?))),
+            None => None,
+        };
+        let tables = Arc::new(Mutex::new(TableRegistry::default()));
 
         sheets.into_iter().try_for_each(|name| -> Result<()> {
-            let sheet_writer = SheetWriter::new(&name, Arc::clone(&style_reg))?;
+            let sheet_writer = SheetWriter::new(
+                &name,
+                Arc::clone(&style_reg),
+                shared_strings.as_ref().map(Arc::clone),
+                Arc::clone(&tables),
+                options.date_1904,
+                options.non_finite,
+            )?;
             insertion_order.push(name.clone());
             _sheets.insert(name, sheet_writer);
             Ok(())
@@ -121,8 +311,18 @@ impl Workbook {
         Ok(Self {
             output_path: path,
             sheets: _sheets,
+            detached: HashSet::new(),
+            chartsheets: HashMap::new(),
             insertion_order,
             style_reg,
+            shared_strings,
+            tables,
+            active_sheet: None,
+            defined_names: Vec::new(),
+            properties: options.properties,
+            date_1904: options.date_1904,
+            non_finite: options.non_finite,
+            calc: options.calc,
         })
     }
 
@@ -131,12 +331,19 @@ impl Workbook {
     }
 
     pub fn add_sheet(&mut self, name: &str) -> Result<&mut SheetWriter> {
-        if self.sheets.contains_key(name) {
+        if self.has_worksheet(name) || self.chartsheets.contains_key(name) {
             return Err(MrXlsxError::AlreadyExists(format!(
                 "Sheet '{name}' already exists"
             )));
         }
-        let writer = SheetWriter::new(name, Arc::clone(&self.style_reg))?;
+        let writer = SheetWriter::new(
+            name,
+            Arc::clone(&self.style_reg),
+            self.shared_strings.as_ref().map(Arc::clone),
+            Arc::clone(&self.tables),
+            self.date_1904,
+            self.non_finite,
+        )?;
         self.sheets.insert(name.to_string(), writer);
         self.insertion_order.push(name.to_string());
         let sheet = match self.sheets.get_mut(name) {
@@ -146,146 +353,534 @@ impl Workbook {
         Ok(sheet)
     }
 
+    /// Take a sheet out of the workbook as an owned handle, e.g. to fill it on another
+    /// thread. It keeps its tab position and must be given back with `attach_sheet`
+    /// before the workbook is finished.
+    pub fn detach_sheet(&mut self, name: &str) -> Result<SheetWriter> {
+        match self.sheets.remove(name) {
+            Some(sheet) => {
+                self.detached.insert(name.to_string());
+                Ok(sheet)
+            }
+            None => Err(MrXlsxError::NotFound(format!("Sheet {name} not found!!"))),
+        }
+    }
+
+    /// Return a sheet taken out with `detach_sheet`.
+    pub fn attach_sheet(&mut self, sheet: SheetWriter) -> Result<()> {
+        let name = sheet.get_name();
+        if !self.detached.contains(&name) || !sheet.shares_styles(&self.style_reg) {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "Sheet '{name}' was not detached from this workbook"
+            )));
+        }
+        self.detached.remove(&name);
+        self.sheets.insert(name, sheet);
+        Ok(())
+    }
+
+    fn has_worksheet(&self, name: &str) -> bool {
+        self.sheets.contains_key(name) || self.detached.contains(name)
+    }
+
+    /// Add a tab holding only `chart`. It takes part in the tab order like any sheet.
+    pub fn add_chartsheet(&mut self, name: &str, chart: Chart) -> Result<()> {
+        if self.has_worksheet(name) || self.chartsheets.contains_key(name) {
+            return Err(MrXlsxError::AlreadyExists(format!(
+                "Sheet '{name}' already exists"
+            )));
+        }
+        chart.validate()?;
+        self.chartsheets.insert(name.to_string(), chart);
+        self.insertion_order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Select the sheet shown when the workbook is opened. Defaults to the first tab.
+    pub fn set_active_sheet(&mut self, name: &str) -> Result<()> {
+        if !self.has_worksheet(name) && !self.chartsheets.contains_key(name) {
+            return Err(MrXlsxError::NotFound(format!("Sheet {name} not found!!")));
+        }
+        self.active_sheet = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Calculation mode and recalculation settings, see [`CalcOptions`].
+    pub fn set_calc_options(&mut self, calc: CalcOptions) {
+        self.calc = calc;
+    }
+
+    /// Replace the document properties, see [`DocProperties`].
+    pub fn set_properties(&mut self, properties: DocProperties) {
+        self.properties = properties;
+    }
+
+    /// Define a workbook-scoped name for a range, constant or formula, e.g.
+    /// `define_name("Rate", "0.2")` or `define_name("Data", "'Sheet (1)'!$A$1:$C$10")`.
+    pub fn define_name(&mut self, name: &str, value: &str) -> Result<()> {
+        self.push_defined_name(None, name, value)
+    }
+
+    /// Define a name visible only to formulas on `sheet`.
+    pub fn define_sheet_name(&mut self, sheet: &str, name: &str, value: &str) -> Result<()> {
+        if !self.has_worksheet(sheet) {
+            return Err(MrXlsxError::NotFound(format!("Sheet {sheet} not found!!")));
+        }
+        self.push_defined_name(Some(sheet.to_string()), name, value)
+    }
+
+    /// Define a workbook-scoped name for a block of cells on `sheet`, 0-based and inclusive.
+    pub fn define_range_name(
+        &mut self,
+        name: &str,
+        sheet: &str,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+    ) -> Result<()> {
+        if !self.has_worksheet(sheet) {
+            return Err(MrXlsxError::NotFound(format!("Sheet {sheet} not found!!")));
+        }
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        let value = format!("{}!{}", quote_sheet_name(sheet), range.to_absolute_ref());
+        self.push_defined_name(None, name, &value)
+    }
+
+    fn push_defined_name(&mut self, scope: Option<String>, name: &str, value: &str) -> Result<()> {
+        if !is_valid_name(name) || name.to_lowercase().starts_with("_xlnm.") {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "'{name}' is not a valid defined name"
+            )));
+        }
+        let value = value.strip_prefix('=').unwrap_or(value);
+        if value.is_empty() {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "defined name '{name}' has no value"
+            )));
+        }
+        let exists = self
+            .defined_names
+            .iter()
+            .any(|(s, n)| *s == scope && n.name.eq_ignore_ascii_case(name));
+        if exists {
+            return Err(MrXlsxError::AlreadyExists(format!(
+                "Defined name '{name}' already exists"
+            )));
+        }
+        self.defined_names.push((
+            scope,
+            DefinedName {
+                name: name.to_string(),
+                local_sheet_id: None,
+                hidden: false,
+                value: value.to_string(),
+            },
+        ));
+        Ok(())
+    }
+
     pub fn finish_by_order(mut self, sheet_order: &[&str]) -> Result<()> {
-        let _sheet_order = sheet_order
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
+        let insertion_order = self.tab_order(sheet_order);
+        self.prepare(&insertion_order)?;
+        let output_file = self.create_output_file()?;
+        self.write_package(insertion_order, ZipWriter::new(output_file))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        let insertion_order = self.insertion_order.clone();
+        self.prepare(&insertion_order)?;
+        let output_file = self.create_output_file()?;
+        self.write_package(insertion_order, ZipWriter::new(output_file))?;
+        Ok(())
+    }
+
+    /// Write the package to `writer` instead of the output path, e.g. an open file or
+    /// a `Cursor<Vec<u8>>`, and hand the writer back.
+    pub fn finish_into<W: Write + Seek>(mut self, writer: W) -> Result<W> {
+        let insertion_order = self.insertion_order.clone();
+        self.prepare(&insertion_order)?;
+        self.write_package(insertion_order, ZipWriter::new(writer))
+    }
 
+    /// Stream the package to a writer that cannot seek, e.g. stdout or an HTTP body.
+    /// Entries are written once, front to back, with sizes in trailing data descriptors.
+    pub fn finish_stream<W: Write>(mut self, writer: W) -> Result<W> {
+        let insertion_order = self.insertion_order.clone();
+        self.prepare(&insertion_order)?;
+        let stream = self.write_package(insertion_order, ZipWriter::new_stream(writer))?;
+        Ok(stream.into_inner())
+    }
+
+    pub fn finish_by_order_into<W: Write + Seek>(
+        mut self,
+        sheet_order: &[&str],
+        writer: W,
+    ) -> Result<W> {
+        let insertion_order = self.tab_order(sheet_order);
+        self.prepare(&insertion_order)?;
+        self.write_package(insertion_order, ZipWriter::new(writer))
+    }
+
+    pub fn finish_by_order_stream<W: Write>(
+        mut self,
+        sheet_order: &[&str],
+        writer: W,
+    ) -> Result<W> {
+        let insertion_order = self.tab_order(sheet_order);
+        self.prepare(&insertion_order)?;
+        let stream = self.write_package(insertion_order, ZipWriter::new_stream(writer))?;
+        Ok(stream.into_inner())
+    }
+
+    /// Build the whole package in memory and return its bytes.
+    pub fn finish_to_vec(self) -> Result<Vec<u8>> {
+        Ok(self.finish_into(Cursor::new(Vec::new()))?.into_inner())
+    }
+
+    // `sheet_order` first, then the remaining sheets in insertion order
+    fn tab_order(&self, sheet_order: &[&str]) -> Vec<String> {
         let mut insertion_order = vec![];
         insertion_order.extend(
             sheet_order
-                .into_iter()
+                .iter()
                 .map(|s| s.to_string())
                 .collect::<Vec<String>>(),
         );
-        self.insertion_order.into_iter().for_each(|sheet| {
-            if !insertion_order.contains(&sheet) {
-                insertion_order.push(sheet);
+        self.insertion_order.iter().for_each(|sheet| {
+            if !insertion_order.contains(sheet) {
+                insertion_order.push(sheet.clone());
             }
         });
+        insertion_order
+    }
 
-         for name in &insertion_order {
-            match self.sheets.get_mut(name) {
-                Some(s) => s.finalize()?,
-                None => {
-                    return Err(MrXlsxError::NotFound(format!("Sheet name : {name}!!")));
-                }
-            }
+    fn create_output_file(&self) -> Result<File> {
+        match &self.output_path {
+            Some(path) => Ok(File::create(path)?),
+            None => Err(MrXlsxError::InvalidArgument(
+                "Workbook has no output path, use finish_into or finish_to_vec".into(),
+            )),
         }
-       
-       let output_file = File::create(&self.output_path)?;
-        let mut zip = ZipWriter::new(output_file);
-        let options =
-            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
-
-        zip_write_str(
-            &mut zip,
-            "[Content_Types].xml",
-            &content_types_xml(insertion_order.len()),
-            options,
-        )?;
-        zip_write_str(&mut zip, "_rels/.rels", RELS_DOT_RELS, options)?;
-        zip_write_str(
-            &mut zip,
-            "xl/workbook.xml",
-            &workbook_xml(&insertion_order),
-            options,
-        )?;
-        zip_write_str(
-            &mut zip,
-            "xl/_rels/workbook.xml.rels",
-            &workbook_rels_xml(insertion_order.len()),
-            options,
-        )?;
-
-        let styles_xml = self.style_reg.lock().unwrap().to_xml();
-        zip_write_str(&mut zip, "xl/styles.xml", &styles_xml, options)?;
-
-        for (i, name) in insertion_order.iter().enumerate() {
-            let sheet = self.sheets.get_mut(name).unwrap();
-            let zip_path = format!("xl/worksheets/sheet{}.xml", i + 1);
+    }
 
-            zip.start_file(&zip_path, options)?;
+    // finalize the sheets and reject the workbook before any output is created,
+    // so a failed finish leaves no empty or partial file behind
+    fn prepare(&mut self, order: &[String]) -> Result<()> {
+        if let Some(name) = self.detached.iter().next() {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "Sheet '{name}' is still detached, attach it before finishing"
+            )));
+        }
+        if let Some(name) = order
+            .iter()
+            .find(|name| !self.sheets.contains_key(*name) && !self.chartsheets.contains_key(*name))
+        {
+            return Err(MrXlsxError::NotFound(format!("Sheet name : {name}!!")));
+        }
+        let tables = self.tables.lock().unwrap();
+        if let Some((_, defined_name)) = self
+            .defined_names
+            .iter()
+            .find(|(_, defined_name)| tables.contains(&defined_name.name))
+        {
+            return Err(MrXlsxError::AlreadyExists(format!(
+                "Defined name '{}' is also a table name",
+                defined_name.name
+            )));
+        }
+        drop(tables);
+        self.properties.validate()?;
+        for name in order {
+            if let Some(sheet) = self.sheets.get_mut(name) {
+                sheet.finalize()?;
+            }
+        }
+        Ok(())
+    }
 
-            let temp_file = sheet.temp.get_mut();
-            temp_file.seek(SeekFrom::Start(0))?;
+    /// Write the package of a workbook checked with `prepare`.
+    fn write_package<W: Write + Seek>(
+        mut self,
+        order: Vec<String>,
+        mut zip: ZipWriter<W>,
+    ) -> Result<W> {
+        let has_shared_strings = self.shared_strings.is_some();
+        let active_tab = self
+            .active_sheet
+            .as_ref()
+            .and_then(|active| order.iter().position(|name| name == active))
+            .unwrap_or(0);
 
-            let mut buf = [0u8; 64 * 1024];
-            loop {
-                use std::io::Read;
-                let n = temp_file.read(&mut buf)?;
-                if n == 0 {
-                    break;
-                }
-                zip.write_all(&buf[..n])?;
+        let mut defined_names = vec![];
+        for (i, name) in order.iter().enumerate() {
+            let Some(sheet) = self.sheets.get(name) else {
+                continue;
+            };
+            let quoted = quote_sheet_name(name);
+            if let Some(range) = sheet.autofilter_range() {
+                defined_names.push(DefinedName {
+                    name: "_xlnm._FilterDatabase".into(),
+                    local_sheet_id: Some(i),
+                    hidden: true,
+                    value: format!("{quoted}!{}", range.to_absolute_ref()),
+                });
+            }
+            if let Some(range) = sheet.print_area() {
+                defined_names.push(DefinedName {
+                    name: "_xlnm.Print_Area".into(),
+                    local_sheet_id: Some(i),
+                    hidden: false,
+                    value: format!("{quoted}!{}", range.to_absolute_ref()),
+                });
+            }
+            let mut titles = vec![];
+            if let Some((first, last)) = sheet.print_title_cols() {
+                titles.push(format!(
+                    "{quoted}!${}:${}",
+                    col_to_letters(first),
+                    col_to_letters(last)
+                ));
+            }
+            if let Some((first, last)) = sheet.print_title_rows() {
+                titles.push(format!("{quoted}!${}:${}", first + 1, last + 1));
+            }
+            if !titles.is_empty() {
+                defined_names.push(DefinedName {
+                    name: "_xlnm.Print_Titles".into(),
+                    local_sheet_id: Some(i),
+                    hidden: false,
+                    value: titles.join(","),
+                });
             }
         }
 
-        zip.finish()?;
-        Ok(())
-    }
+        for (scope, mut defined_name) in std::mem::take(&mut self.defined_names) {
+            defined_name.local_sheet_id =
+                scope.and_then(|sheet| order.iter().position(|name| *name == sheet));
+            defined_names.push(defined_name);
+        }
 
-    pub fn finish(mut self) -> Result<()> {
-        for name in &self.insertion_order {
-            match self.sheets.get_mut(name) {
-                Some(s) => s.finalize()?,
-                None => {
-                    return Err(MrXlsxError::NotFound(format!("Sheet name : {name}!!")));
-                }
+        let mut defaults = vec![];
+        let mut overrides = vec![
+            ("/docProps/core.xml".to_string(), CT_CORE_PROPERTIES),
+            ("/docProps/app.xml".to_string(), CT_EXTENDED_PROPERTIES),
+        ];
+        let has_custom_properties = !self.properties.custom.is_empty();
+        if has_custom_properties {
+            overrides.push(("/docProps/custom.xml".to_string(), CT_CUSTOM_PROPERTIES));
+        }
+        if has_shared_strings {
+            overrides.push(("/xl/sharedStrings.xml".to_string(), CT_SHARED_STRINGS));
+        }
+        let has_metadata = self.sheets.values().any(|s| s.has_dynamic_arrays());
+        if has_metadata {
+            overrides.push(("/xl/metadata.xml".to_string(), CT_SHEET_METADATA));
+        }
+        let mut part_ids = vec![];
+        let (mut table_count, mut comments_count, mut drawing_count, mut chart_count) =
+            (0, 0, 0, 0);
+        let mut vml_block = 1;
+        let mut sheet_rels = vec![];
+        // identical images are stored once
+        let mut media: Vec<(String, &[u8])> = vec![];
+        let mut media_index: HashMap<&[u8], usize> = HashMap::new();
+        for (i, name) in order.iter().enumerate() {
+            let Some(sheet) = self.sheets.get(name) else {
+                // a chartsheet is a drawing holding a single chart
+                let path = format!("chartsheets/sheet{}.xml", i + 1);
+                overrides.push((format!("/xl/{path}"), CT_CHARTSHEET));
+                sheet_rels.push((REL_CHARTSHEET, path));
+                drawing_count += 1;
+                chart_count += 1;
+                let drawing_path = format!("/xl/drawings/drawing{drawing_count}.xml");
+                overrides.push((drawing_path, CT_DRAWING));
+                overrides.push((format!("/xl/charts/chart{chart_count}.xml"), CT_CHART));
+                part_ids.push(PartIds {
+                    drawing: Some(drawing_count),
+                    first_chart: chart_count,
+                    ..PartIds::default()
+                });
+                continue;
+            };
+            let path = format!("worksheets/sheet{}.xml", i + 1);
+            overrides.push((format!("/xl/{path}"), CT_WORKSHEET));
+            sheet_rels.push((REL_WORKSHEET, path));
+
+            let mut ids = PartIds {
+                first_table: table_count + 1,
+                first_chart: chart_count + 1,
+                ..PartIds::default()
+            };
+            for _ in sheet.tables() {
+                table_count += 1;
+                overrides.push((format!("/xl/tables/table{table_count}.xml"), CT_TABLE));
             }
+            if !sheet.notes().is_empty() {
+                comments_count += 1;
+                overrides.push((format!("/xl/comments{comments_count}.xml"), CT_COMMENTS));
+                ids.comments = Some(comments_count);
+                ids.vml_block = vml_block;
+                vml_block += note::id_blocks(sheet.notes().len());
+            }
+            for image in sheet.images() {
+                let i = *media_index.entry(&image.data).or_insert_with(|| {
+                    let extension = image.format.extension();
+                    if !defaults.iter().any(|(e, _)| *e == extension) {
+                        defaults.push((extension, image.format.content_type()));
+                    }
+                    let path = format!("media/image{}.{extension}", media.len() + 1);
+                    media.push((path, &image.data));
+                    media.len() - 1
+                });
+                ids.media.push(format!("../{}", media[i].0));
+            }
+            for _ in sheet.charts() {
+                chart_count += 1;
+                overrides.push((format!("/xl/charts/chart{chart_count}.xml"), CT_CHART));
+            }
+            if !ids.media.is_empty() || sheet.charts().next().is_some() {
+                drawing_count += 1;
+                let drawing_path = format!("/xl/drawings/drawing{drawing_count}.xml");
+                overrides.push((drawing_path, CT_DRAWING));
+                ids.drawing = Some(drawing_count);
+            }
+            part_ids.push(ids);
+        }
+        if comments_count > 0 {
+            defaults.push(("vml", CT_VML));
         }
 
-        let output_file = File::create(&self.output_path)?;
-        let mut zip = ZipWriter::new(output_file);
         let options =
             SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
         zip_write_str(
             &mut zip,
             "[Content_Types].xml",
-            &content_types_xml(self.insertion_order.len()),
+            &content_types_xml(&defaults, &overrides),
             options,
         )?;
-        zip_write_str(&mut zip, "_rels/.rels", RELS_DOT_RELS, options)?;
+        let mut package_rels = Relationships::default();
+        package_rels.add(REL_OFFICE_DOCUMENT, "xl/workbook.xml", false);
+        package_rels.add(REL_CORE_PROPERTIES, "docProps/core.xml", false);
+        package_rels.add(REL_EXTENDED_PROPERTIES, "docProps/app.xml", false);
+        if has_custom_properties {
+            package_rels.add(REL_CUSTOM_PROPERTIES, "docProps/custom.xml", false);
+        }
+        zip_write_str(&mut zip, "_rels/.rels", &package_rels.to_xml(), options)?;
+
+        let core_xml = self.properties.core_xml(SystemTime::now());
+        zip_write_str(&mut zip, "docProps/core.xml", &core_xml, options)?;
+        let (worksheets, chartsheets): (Vec<&str>, Vec<&str>) = order
+            .iter()
+            .map(String::as_str)
+            .partition(|name| self.sheets.contains_key(*name));
+        let app_xml = self.properties.app_xml(&worksheets, &chartsheets);
+        zip_write_str(&mut zip, "docProps/app.xml", &app_xml, options)?;
+        if has_custom_properties {
+            let custom_xml = self.properties.custom_xml();
+            zip_write_str(&mut zip, "docProps/custom.xml", &custom_xml, options)?;
+        }
         zip_write_str(
             &mut zip,
             "xl/workbook.xml",
-            &workbook_xml(&self.insertion_order),
+            &workbook_xml(
+                &order,
+                active_tab,
+                &defined_names,
+                self.date_1904,
+                &self.calc,
+            ),
             options,
         )?;
         zip_write_str(
             &mut zip,
             "xl/_rels/workbook.xml.rels",
-            &workbook_rels_xml(self.insertion_order.len()),
+            &workbook_rels_xml(&sheet_rels, has_shared_strings, has_metadata),
             options,
         )?;
 
         let styles_xml = self.style_reg.lock().unwrap().to_xml();
         zip_write_str(&mut zip, "xl/styles.xml", &styles_xml, options)?;
 
-        for (i, name) in self.insertion_order.iter().enumerate() {
-            let sheet = self.sheets.get_mut(name).unwrap();
+        if let Some(sst) = &self.shared_strings {
+            zip.start_file("xl/sharedStrings.xml", options)?;
+            sst.lock().unwrap().write_xml(&mut zip)?;
+        }
+        if has_metadata {
+            zip_write_str(&mut zip, "xl/metadata.xml", METADATA_XML, options)?;
+        }
+
+        // images are already compressed
+        let media_options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (path, data) in &media {
+            zip.start_file(format!("xl/{path}"), media_options)?;
+            zip.write_all(data)?;
+        }
+
+        for (i, (name, ids)) in order.iter().zip(&part_ids).enumerate() {
+            let Some(sheet) = self.sheets.get_mut(name) else {
+                let chart = &self.chartsheets[name];
+                let (drawing, n) = (ids.drawing.unwrap(), ids.first_chart);
+                let path = format!("xl/chartsheets/sheet{}.xml", i + 1);
+                zip_write_str(&mut zip, &path, &chartsheet_xml(i == active_tab), options)?;
+
+                let mut rels = Relationships::default();
+                let drawing_target = format!("../drawings/drawing{drawing}.xml");
+                rels.add(REL_DRAWING, &drawing_target, false);
+                let rels_path = format!("xl/chartsheets/_rels/sheet{}.xml.rels", i + 1);
+                zip_write_str(&mut zip, &rels_path, &rels.to_xml(), options)?;
+
+                let mut drawing_rels = Relationships::default();
+                let r_id = drawing_rels.add(REL_CHART, &format!("../charts/chart{n}.xml"), false);
+                let frame = absolute_anchor_xml(&chart_frame_xml(1, &r_id, None));
+                let drawing_path = format!("xl/drawings/drawing{drawing}.xml");
+                zip_write_str(&mut zip, &drawing_path, &drawing_xml(&[frame]), options)?;
+                let rels_path = format!("xl/drawings/_rels/drawing{drawing}.xml.rels");
+                zip_write_str(&mut zip, &rels_path, &drawing_rels.to_xml(), options)?;
+
+                let chart_path = format!("xl/charts/chart{n}.xml");
+                zip_write_str(&mut zip, &chart_path, &chart.to_xml(), options)?;
+                continue;
+            };
             let zip_path = format!("xl/worksheets/sheet{}.xml", i + 1);
 
             zip.start_file(&zip_path, options)?;
+            let rels = sheet.write_xml(&mut zip, i == active_tab, ids)?;
 
-            let temp_file = sheet.temp.get_mut();
-            temp_file.seek(SeekFrom::Start(0))?;
+            for (j, table) in sheet.tables().iter().enumerate() {
+                let table_id = ids.first_table + j;
+                let table_path = format!("xl/tables/table{table_id}.xml");
+                zip_write_str(&mut zip, &table_path, &table.to_xml(table_id), options)?;
+            }
+            for (j, chart) in sheet.charts().enumerate() {
+                let chart_path = format!("xl/charts/chart{}.xml", ids.first_chart + j);
+                zip_write_str(&mut zip, &chart_path, &chart.to_xml(), options)?;
+            }
+            if let Some(n) = ids.drawing {
+                let (xml, drawing_rels) = sheet.drawing_xml(&ids.media, ids.first_chart);
+                let drawing_path = format!("xl/drawings/drawing{n}.xml");
+                zip_write_str(&mut zip, &drawing_path, &xml, options)?;
+                let rels_path = format!("xl/drawings/_rels/drawing{n}.xml.rels");
+                zip_write_str(&mut zip, &rels_path, &drawing_rels.to_xml(), options)?;
+            }
+            if let Some(n) = ids.comments {
+                let notes = sheet.notes();
+                let comments_path = format!("xl/comments{n}.xml");
+                zip_write_str(&mut zip, &comments_path, &comments_xml(notes), options)?;
+                let vml_path = format!("xl/drawings/vmlDrawing{n}.vml");
+                zip_write_str(&mut zip, &vml_path, &sheet.vml_xml(ids.vml_block), options)?;
+            }
 
-            let mut buf = [0u8; 64 * 1024];
-            loop {
-                use std::io::Read;
-                let n = temp_file.read(&mut buf)?;
-                if n == 0 {
-                    break;
-                }
-                zip.write_all(&buf[..n])?;
+            if !rels.is_empty() {
+                let rels_path = format!("xl/worksheets/_rels/sheet{}.xml.rels", i + 1);
+                zip_write_str(&mut zip, &rels_path, &rels.to_xml(), options)?;
             }
         }
 
-        zip.finish()?;
-        Ok(())
+        Ok(zip.finish()?)
     }
 }
 
@@ -323,12 +918,48 @@ pub(crate) fn xml_escape(s: &str) -> String {
     }
     out
 }
+/// Quote a sheet name for use in a formula reference, e.g. `'Sheet (1)'`.
+pub fn quote_sheet_name(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !looks_like_cell_ref(name);
+    if plain {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+// "A1", "XFD100", "R1C1", "R" and "C" would be read as references
+pub(crate) fn looks_like_cell_ref(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    let letters = upper.trim_end_matches(|c: char| c.is_ascii_digit());
+    let a1 = letters.len() < upper.len()
+        && (1..=3).contains(&letters.len())
+        && letters.chars().all(|c| c.is_ascii_uppercase());
+    let r1c1 = upper
+        .strip_prefix('R')
+        .map(|rest| rest.trim_start_matches(|c: char| c.is_ascii_digit()))
+        .and_then(|rest| rest.strip_prefix('C').or(Some(rest)))
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()));
+    let c1 = upper
+        .strip_prefix('C')
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()));
+    a1 || r1c1 || c1
+}
+
 pub(crate) fn write_cell<W: Write>(
     w: &mut W,
-    cell_ref: &str,
+    row: u32,
+    col: u32,
     value: &CellValue,
     style_idx: Option<usize>,
+    shared_strings: Option<&mut SharedStringTable>,
+    date_1904: bool,
 ) -> Result<()> {
+    let cell_ref = &make_cell_ref(row, col);
     let s = match style_idx {
         Some(0) | None => String::new(),
         Some(n) => format!(" s=\"{n}\""),
@@ -341,20 +972,66 @@ pub(crate) fn write_cell<W: Write>(
         CellValue::Number(n) => {
             write!(w, "<c r=\"{cell_ref}\"{s}><v>{n}</v></c>")?;
         }
-        CellValue::Text(text) => {
-            let escaped = xml_escape(text);
-            write!(
-                w,
-                "<c r=\"{cell_ref}\"{s} t=\"inlineStr\"><is><t>{escaped}</t></is></c>"
-            )?;
+        CellValue::Text(text) => write_text_cell(w, cell_ref, &s, text, shared_strings)?,
+        CellValue::RichText(runs) => match shared_strings {
+            Some(sst) => {
+                let idx = sst.intern_rich(runs)?;
+                write!(w, "<c r=\"{cell_ref}\"{s} t=\"s\"><v>{idx}</v></c>")?;
+            }
+            None => {
+                let r = runs_xml(runs);
+                write!(w, "<c r=\"{cell_ref}\"{s} t=\"inlineStr\"><is>{r}</is></c>")?;
+            }
+        },
+        CellValue::Hyperlink(link) => {
+            write_text_cell(w, cell_ref, &s, link.display_text(), shared_strings)?
         }
         CellValue::Bool(b) => {
             let val = if *b { 1 } else { 0 };
             write!(w, "<c r=\"{cell_ref}\"{s} t=\"b\"><v>{val}</v></c>")?;
         }
-        CellValue::Formula(f) => {
-            let escaped = xml_escape(f);
-            write!(w, "<c r=\"{cell_ref}\"{s}><f>{escaped}</f><v/></c>")?;
+        CellValue::Formula(formula) => {
+            let (attrs, content) = formula.to_xml(row, col);
+            write!(w, "<c r=\"{cell_ref}\"{s}{attrs}>{content}</c>")?;
+        }
+        CellValue::Date(date) => {
+            let serial = date.serial(date_1904)?;
+            write!(w, "<c r=\"{cell_ref}\"{s}><v>{serial}</v></c>")?;
+        }
+        CellValue::Time(time) => {
+            write!(w, "<c r=\"{cell_ref}\"{s}><v>{}</v></c>", time.serial())?;
+        }
+        CellValue::DateTime(datetime) => {
+            let serial = datetime.serial(date_1904)?;
+            write!(w, "<c r=\"{cell_ref}\"{s}><v>{serial}</v></c>")?;
+        }
+        CellValue::Duration(duration) => {
+            let serial = duration_serial(*duration);
+            write!(w, "<c r=\"{cell_ref}\"{s}><v>{serial}</v></c>")?;
+        }
+        CellValue::Error(e) => {
+            let e = e.as_str();
+            write!(w, "<c r=\"{cell_ref}\"{s} t=\"e\"><v>{e}</v></c>")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_text_cell<W: Write>(
+    w: &mut W,
+    cell_ref: &str,
+    s: &str,
+    text: &str,
+    shared_strings: Option<&mut SharedStringTable>,
+) -> Result<()> {
+    match shared_strings {
+        Some(sst) => {
+            let idx = sst.intern(text)?;
+            write!(w, "<c r=\"{cell_ref}\"{s} t=\"s\"><v>{idx}</v></c>")?;
+        }
+        None => {
+            let t = text_xml(text);
+            write!(w, "<c r=\"{cell_ref}\"{s} t=\"inlineStr\"><is>{t}</is></c>")?;
         }
     }
     Ok(())
@@ -370,3 +1047,532 @@ pub(crate) fn zip_write_str<W: Write + Seek>(
     zip.write_all(content.as_bytes())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        csv::reader::XlsxReader,
+        error::MrXlsxError,
+        workbook::{
+            builder::WorkbookBuilder,
+            cell::CellValue,
+            chart::{Chart, ChartSeries, ChartType, range_ref},
+            conditional::ConditionalFormat,
+            formula::Formula,
+            hyperlink::Hyperlink,
+            image::Image,
+            note::Note,
+            properties::DocProperties,
+            quote_sheet_name,
+            style::Style,
+            table::{Table, TableColumn, TotalFunction},
+            testing::{attr_map, attrs, children, from_bytes, targets, write, write_with},
+        },
+    };
+
+    #[test]
+    fn shared_strings_round_trip() {
+        let written = write_with(
+            |builder| builder.shared_strings(true),
+            |wb| {
+                let sheet = wb.add_sheet("Data").unwrap();
+                sheet
+                    .write_row(&[CellValue::text("a"), CellValue::text(" b")])
+                    .unwrap();
+                sheet
+                    .write_row(&[CellValue::text("a"), CellValue::Number(2.0)])
+                    .unwrap();
+            },
+        );
+
+        let sst = &attrs(&written.part("xl/sharedStrings.xml"), "sst")[0];
+        assert_eq!((&*sst["count"], &*sst["uniqueCount"]), ("3", "2"));
+        assert_eq!(written.rows("Data"), [vec!["a", " b"], vec!["a", "2"]]);
+    }
+
+    #[test]
+    fn hyperlinks_round_trip() {
+        let written = write(|wb| {
+            wb.add_sheet("Data")
+                .unwrap()
+                .write_row(&[
+                    CellValue::Hyperlink(Hyperlink::url("https://example.com/a#top").text("Site")),
+                    CellValue::Hyperlink(Hyperlink::internal("Data!B2")),
+                ])
+                .unwrap();
+        });
+
+        let rels = written.part("xl/worksheets/_rels/sheet1.xml.rels");
+        let rel = &attrs(&rels, "Relationship")[0];
+        assert_eq!(rel["Target"], "https://example.com/a");
+        assert_eq!(rel["TargetMode"], "External");
+        let sheet = written.part("xl/worksheets/sheet1.xml");
+        assert_eq!(
+            attrs(&sheet, "hyperlink"),
+            [
+                attr_map([
+                    ("ref", "A1"),
+                    ("r:id", "rId1"),
+                    ("location", "top"),
+                    ("display", "Site"),
+                ]),
+                attr_map([("ref", "B1"), ("location", "Data!B2")]),
+            ]
+        );
+        assert_eq!(
+            children(&sheet, "worksheet"),
+            [
+                "sheetViews",
+                "sheetFormatPr",
+                "sheetData",
+                "hyperlinks",
+                "pageMargins"
+            ]
+        );
+        assert_eq!(written.rows("Data"), [vec!["Site", "Data!B2"]]);
+    }
+
+    #[test]
+    fn tables_round_trip() {
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("Data").unwrap();
+            let table = Table::new().name("Stock").totals_row().columns(vec![
+                TableColumn::new("Item").total_label("Total"),
+                TableColumn::new("Qty").total_function(TotalFunction::Sum),
+            ]);
+            sheet.add_table(0, 0, 2, 1, table).unwrap();
+            sheet.write_row(&[]).unwrap();
+            sheet
+                .write_row(&[CellValue::text("Pens"), CellValue::Number(3.0)])
+                .unwrap();
+        });
+
+        let table = &attrs(&written.part("xl/tables/table1.xml"), "table")[0];
+        assert_eq!(table["name"], "Stock");
+        assert_eq!(table["displayName"], "Stock");
+        assert_eq!(table["ref"], "A1:B3");
+        assert_eq!(table["totalsRowCount"], "1");
+        let rels = written.part("xl/worksheets/_rels/sheet1.xml.rels");
+        assert_eq!(targets(&rels), ["../tables/table1.xml"]);
+        let sheet = written.part("xl/worksheets/sheet1.xml");
+        assert_eq!(children(&sheet, "worksheet").last().unwrap(), "tableParts");
+        assert_eq!(
+            written.rows("Data"),
+            [vec!["Item", "Qty"], vec!["Pens", "3"], vec!["Total", ""]]
+        );
+    }
+
+    #[test]
+    fn sheet_names_are_quoted_when_needed() {
+        assert_eq!(quote_sheet_name("Data"), "Data");
+        assert_eq!(quote_sheet_name("Sales.2024"), "Sales.2024");
+        assert_eq!(quote_sheet_name("Données"), "Données");
+        assert_eq!(quote_sheet_name("Sheet 1"), "'Sheet 1'");
+        assert_eq!(quote_sheet_name("O'Brien"), "'O''Brien'");
+        assert_eq!(quote_sheet_name("2024"), "'2024'");
+        for reference in ["A1", "xfd100", "R1C1", "R", "C3"] {
+            assert_eq!(quote_sheet_name(reference), format!("'{reference}'"));
+        }
+    }
+
+    #[test]
+    fn conditional_formats_round_trip() {
+        let bold = || ConditionalFormat::duplicates().style(Style::new().bold());
+        let red = ConditionalFormat::top(3).style(Style::new().font_color("FF0000"));
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("First").unwrap();
+            sheet.write_row(&[CellValue::Number(1.0)]).unwrap();
+            sheet.add_conditional_format(0, 0, 9, 0, red).unwrap();
+            sheet.add_conditional_format(0, 1, 9, 1, bold()).unwrap();
+            let sheet = wb.add_sheet("Second").unwrap();
+            sheet.add_conditional_format(0, 0, 0, 3, bold()).unwrap();
+            sheet
+                .add_conditional_format(1, 0, 1, 3, ConditionalFormat::data_bar("638EC6"))
+                .unwrap();
+        });
+
+        let styles = written.part("xl/styles.xml");
+        assert_eq!(children(&styles, "dxfs"), ["dxf", "dxf"]);
+        assert!(
+            styles.contains(concat!(
+                "<dxf><font><color rgb=\"FFFF0000\"/></font></dxf>\n",
+                "<dxf><font><b/></font></dxf>\n</dxfs>",
+            )),
+            "{styles}"
+        );
+        let first = written.part("xl/worksheets/sheet1.xml");
+        let ranges: Vec<_> = attrs(&first, "conditionalFormatting")
+            .into_iter()
+            .map(|cf| cf["sqref"].clone())
+            .collect();
+        assert_eq!(ranges, ["A1:A10", "B1:B10"]);
+        assert_eq!(
+            attrs(&first, "cfRule"),
+            [
+                attr_map([
+                    ("type", "top10"),
+                    ("dxfId", "0"),
+                    ("priority", "1"),
+                    ("rank", "3")
+                ]),
+                attr_map([
+                    ("type", "duplicateValues"),
+                    ("dxfId", "1"),
+                    ("priority", "2")
+                ]),
+            ]
+        );
+        let second = written.part("xl/worksheets/sheet2.xml");
+        assert_eq!(
+            attrs(&second, "cfRule"),
+            [
+                attr_map([
+                    ("type", "duplicateValues"),
+                    ("dxfId", "1"),
+                    ("priority", "1")
+                ]),
+                attr_map([("type", "dataBar"), ("priority", "2")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn notes_round_trip() {
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("Data").unwrap();
+            sheet.write_row(&[CellValue::text("Total")]).unwrap();
+            sheet
+                .add_note(0, 0, Note::new("Checked").author("Ann"))
+                .unwrap();
+        });
+
+        let comments = written.part("xl/comments1.xml");
+        assert!(comments.contains("<author>Ann</author>"), "{comments}");
+        assert_eq!(
+            attrs(&comments, "comment"),
+            [attr_map([("ref", "A1"), ("authorId", "0")])]
+        );
+        let vml = written.part("xl/drawings/vmlDrawing1.vml");
+        assert!(
+            vml.contains("<x:Row>0</x:Row><x:Column>0</x:Column>"),
+            "{vml}"
+        );
+        let rels = written.part("xl/worksheets/_rels/sheet1.xml.rels");
+        assert_eq!(
+            targets(&rels),
+            ["../comments1.xml", "../drawings/vmlDrawing1.vml"]
+        );
+        let sheet = written.part("xl/worksheets/sheet1.xml");
+        assert_eq!(
+            children(&sheet, "worksheet").last().unwrap(),
+            "legacyDrawing"
+        );
+        assert_eq!(written.rows("Data"), [vec!["Total"]]);
+    }
+
+    #[test]
+    fn note_shape_ids_are_unique_across_sheets() {
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("Many").unwrap();
+            for row in 0..1100 {
+                sheet.add_note(row, 0, Note::new("x")).unwrap();
+            }
+            wb.add_sheet("One")
+                .unwrap()
+                .add_note(0, 0, Note::new("y"))
+                .unwrap();
+        });
+
+        let vml = written.part("xl/drawings/vmlDrawing1.vml");
+        assert_eq!(attrs(&vml, "o:idmap")[0]["data"], "1,2");
+        let shapes = attrs(&vml, "v:shape");
+        assert_eq!(shapes[0]["id"], "_x0000_s1025");
+        assert_eq!(shapes[1099]["id"], "_x0000_s2124");
+        let vml = written.part("xl/drawings/vmlDrawing2.vml");
+        assert_eq!(attrs(&vml, "o:idmap")[0]["data"], "3");
+        assert_eq!(attrs(&vml, "v:shape")[0]["id"], "_x0000_s3073");
+    }
+
+    // PNG header with just enough for Image::new to read the size
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        data.extend(width.to_be_bytes());
+        data.extend(height.to_be_bytes());
+        data.extend([8, 6, 0, 0, 0, 0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn images_round_trip() {
+        let data = png(64, 32);
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("Data").unwrap();
+            sheet.write_row(&[CellValue::text("Logo")]).unwrap();
+            sheet
+                .insert_image(1, 1, Image::new(data.clone()).unwrap().alt_text("Logo"))
+                .unwrap();
+        });
+
+        let drawing = written.part("xl/drawings/drawing1.xml");
+        assert!(
+            drawing.contains("<xdr:col>1</xdr:col><xdr:colOff>0</xdr:colOff><xdr:row>1</xdr:row>"),
+            "{drawing}"
+        );
+        assert_eq!(attrs(&drawing, "xdr:cNvPr")[0]["descr"], "Logo");
+        let rels = written.part("xl/drawings/_rels/drawing1.xml.rels");
+        assert_eq!(targets(&rels), ["../media/image1.png"]);
+        assert_eq!(written.bytes("xl/media/image1.png"), data);
+        let content_types = written.part("[Content_Types].xml");
+        assert!(
+            attrs(&content_types, "Default").contains(&attr_map([
+                ("Extension", "png"),
+                ("ContentType", "image/png")
+            ])),
+            "{content_types}"
+        );
+        assert_eq!(written.rows("Data"), [vec!["Logo"]]);
+    }
+
+    #[test]
+    fn charts_round_trip() {
+        let values = range_ref("Data", 0, 0, 1, 0).unwrap();
+        let chart = || Chart::new(ChartType::Column).add_series(ChartSeries::new(&values));
+        let written = write(|wb| {
+            let sheet = wb.add_sheet("Data").unwrap();
+            sheet.write_row(&[CellValue::Number(1.0)]).unwrap();
+            sheet.write_row(&[CellValue::Number(2.0)]).unwrap();
+            sheet.insert_chart(0, 2, chart().title("On sheet")).unwrap();
+            wb.add_chartsheet("Chart", chart().title("Own tab"))
+                .unwrap();
+        });
+
+        let reader = XlsxReader::open(&written.path).unwrap();
+        assert_eq!(reader.sheet_names(), ["Data"]);
+        let workbook = written.part("xl/workbook.xml");
+        let sheets: Vec<_> = attrs(&workbook, "sheet")
+            .into_iter()
+            .map(|sheet| (sheet["name"].clone(), sheet["sheetId"].clone()))
+            .collect();
+        assert_eq!(
+            sheets,
+            [("Data".into(), "1".into()), ("Chart".into(), "2".into())]
+        );
+        assert_eq!(written.rows("Data"), [vec!["1"], vec!["2"]]);
+
+        let mut titles = vec![];
+        for n in 1..=2 {
+            let chart = written.part(&format!("xl/charts/chart{n}.xml"));
+            assert!(chart.contains("<c:f>Data!$A$1:$A$2</c:f>"), "{chart}");
+            titles.extend(
+                ["On sheet", "Own tab"]
+                    .into_iter()
+                    .filter(|t| chart.contains(*t)),
+            );
+        }
+        titles.sort();
+        assert_eq!(titles, ["On sheet", "Own tab"]);
+        let chartsheet = written.part("xl/chartsheets/sheet2.xml");
+        assert_eq!(attrs(&chartsheet, "drawing")[0]["r:id"], "rId1");
+        for n in 1..=2 {
+            let drawing = written.part(&format!("xl/drawings/drawing{n}.xml"));
+            assert_eq!(attrs(&drawing, "c:chart").len(), 1, "{drawing}");
+        }
+    }
+
+    #[test]
+    fn properties_round_trip() {
+        let created = UNIX_EPOCH + Duration::from_secs(1_709_285_400);
+        let properties = DocProperties::new()
+            .title("Report")
+            .author("Ann")
+            .created(created)
+            .custom("Reviewed", true)
+            .custom("Score", 1.5);
+        let written = write_with(
+            |builder| builder.properties(properties),
+            |wb| {
+                wb.add_sheet("Data")
+                    .unwrap()
+                    .write_row(&[CellValue::text("x")])
+                    .unwrap();
+            },
+        );
+
+        let core = written.part("docProps/core.xml");
+        assert!(core.contains("<dc:title>Report</dc:title>"), "{core}");
+        assert!(core.contains("<dc:creator>Ann</dc:creator>"), "{core}");
+        assert!(
+            core.contains(r#"<dcterms:created xsi:type="dcterms:W3CDTF">2024-03-01T09:30:00Z</dcterms:created>"#),
+            "{core}"
+        );
+        let app = written.part("docProps/app.xml");
+        assert!(app.contains("<vt:lpstr>Data</vt:lpstr>"), "{app}");
+        let custom = written.part("docProps/custom.xml");
+        let names: Vec<_> = attrs(&custom, "property")
+            .into_iter()
+            .map(|p| (p["pid"].clone(), p["name"].clone()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("2".into(), "Reviewed".into()),
+                ("3".into(), "Score".into())
+            ]
+        );
+        assert!(
+            custom.contains(r#"name="Reviewed"><vt:bool>true</vt:bool></property>"#),
+            "{custom}"
+        );
+        assert!(
+            custom.contains(r#"name="Score"><vt:r8>1.5</vt:r8></property>"#),
+            "{custom}"
+        );
+        assert!(targets(&written.part("_rels/.rels")).contains(&"docProps/custom.xml".into()));
+        assert_eq!(written.rows("Data"), [vec!["x"]]);
+    }
+
+    #[test]
+    fn dynamic_array_metadata_round_trip() {
+        let written = write_with(
+            |builder| builder.shared_strings(true),
+            |wb| {
+                wb.add_sheet("Data")
+                    .unwrap()
+                    .write_row(&[
+                        CellValue::text("x"),
+                        CellValue::Formula(Formula::new("UNIQUE(A1:A2)").result("x")),
+                    ])
+                    .unwrap();
+            },
+        );
+
+        let metadata = written.part("xl/metadata.xml");
+        assert_eq!(attrs(&metadata, "metadataType")[0]["name"], "XLDAPR");
+        let rels = written.part("xl/_rels/workbook.xml.rels");
+        assert!(
+            attrs(&rels, "Relationship").contains(&attr_map([
+                ("Id", "rId4"),
+                (
+                    "Type",
+                    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/sheetMetadata"
+                ),
+                ("Target", "metadata.xml"),
+            ])),
+            "{rels}"
+        );
+        let sheet = written.part("xl/worksheets/sheet1.xml");
+        assert_eq!(
+            attrs(&sheet, "c")[1],
+            attr_map([("r", "B1"), ("t", "str"), ("cm", "1")])
+        );
+        assert_eq!(
+            attrs(&sheet, "f"),
+            [attr_map([("t", "array"), ("ref", "B1")])]
+        );
+        assert!(sheet.contains(">_xlfn.UNIQUE(A1:A2)</f>"), "{sheet}");
+        assert_eq!(written.rows("Data"), [vec!["x", "x"]]);
+
+        let written = write(|wb| {
+            wb.add_sheet("Data")
+                .unwrap()
+                .write_row(&[CellValue::formula("SUM(1,2)")])
+                .unwrap();
+        });
+        assert!(!written.has_part("xl/metadata.xml"));
+    }
+
+    #[test]
+    fn streamed_workbook_round_trip() {
+        let mut wb = WorkbookBuilder::in_memory()
+            .shared_strings(true)
+            .build()
+            .unwrap();
+        let sheet = wb.add_sheet("Data").unwrap();
+        sheet
+            .write_row(&[CellValue::text("a"), CellValue::Number(1.0)])
+            .unwrap();
+        let written = from_bytes(&wb.finish_stream(Vec::new()).unwrap());
+
+        written.part("xl/sharedStrings.xml");
+        assert_eq!(written.rows("Data"), [vec!["a", "1"]]);
+    }
+
+    #[test]
+    fn detached_sheet_is_filled_on_another_thread() {
+        let written = write(|wb| {
+            wb.add_sheet("First").unwrap();
+            wb.add_sheet("Data").unwrap();
+            let mut sheet = wb.detach_sheet("Data").unwrap();
+            let sheet = std::thread::spawn(move || {
+                for i in 0..3 {
+                    sheet
+                        .write_row(&[CellValue::text("row"), CellValue::Number(i as f64)])
+                        .unwrap();
+                }
+                sheet
+            })
+            .join()
+            .unwrap();
+            wb.attach_sheet(sheet).unwrap();
+        });
+
+        assert_eq!(
+            written.rows("Data"),
+            [vec!["row", "0"], vec!["row", "1"], vec!["row", "2"]]
+        );
+    }
+
+    #[test]
+    fn only_detached_sheets_can_be_attached() {
+        let in_memory = || WorkbookBuilder::<String>::in_memory().build().unwrap();
+        let mut wb = in_memory();
+        let mut other = in_memory();
+        wb.add_sheet("Data").unwrap();
+        other.add_sheet("Data").unwrap();
+        other.add_sheet("Spare").unwrap();
+
+        // a sheet of another workbook is rejected even under a name detached here
+        let own = wb.detach_sheet("Data").unwrap();
+        let foreign = other.detach_sheet("Data").unwrap();
+        assert!(matches!(
+            wb.attach_sheet(foreign),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            wb.attach_sheet(other.detach_sheet("Spare").unwrap()),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+
+        // `attach_sheet` takes the writer, and the name is closed once it is back
+        wb.attach_sheet(own).unwrap();
+        let own = wb.detach_sheet("Data").unwrap();
+        wb.attach_sheet(own).unwrap();
+        let mut third = in_memory();
+        third.add_sheet("Data").unwrap();
+        assert!(matches!(
+            wb.attach_sheet(third.detach_sheet("Data").unwrap()),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        wb.finish_stream(Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn failed_finish_creates_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.xlsx");
+        let path = path.to_str().unwrap();
+
+        let mut wb = WorkbookBuilder::new(path).build().unwrap();
+        wb.add_sheet("Data").unwrap();
+        let _detached = wb.detach_sheet("Data").unwrap();
+        assert!(wb.finish().is_err());
+        assert!(!std::path::Path::new(path).exists());
+
+        let mut wb = WorkbookBuilder::new(path).build().unwrap();
+        wb.add_sheet("Data").unwrap();
+        assert!(wb.finish_by_order(&["Missing"]).is_err());
+        assert!(!std::path::Path::new(path).exists());
+    }
+}
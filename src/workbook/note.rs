@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use crate::workbook::{
+    drawing::Anchor,
+    make_cell_ref,
+    range::MAX_COLS,
+    rich_text::{TextRun, runs_xml},
+    shared_strings::text_xml,
+    xml_escape,
+};
+
+/// A note (legacy comment) attached to a cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub text: Vec<TextRun>,
+    pub author: Option<String>,
+    /// Show the note box permanently instead of on hover.
+    pub visible: bool,
+    /// Box size in points.
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Note {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Note::rich(vec![TextRun::new(text)])
+    }
+
+    pub fn rich(runs: Vec<TextRun>) -> Self {
+        Note {
+            text: runs,
+            author: None,
+            visible: false,
+            width: 96.0,
+            height: 55.5,
+        }
+    }
+
+    pub fn author<S: Into<String>>(mut self, author: S) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+    pub fn visible(mut self) -> Self {
+        self.visible = true;
+        self
+    }
+    pub fn size(mut self, width: f64, height: f64) -> Self {
+        self.width = width.max(1.0);
+        self.height = height.max(1.0);
+        self
+    }
+
+    fn text_xml(&self) -> String {
+        match self.text.as_slice() {
+            [run] if run.font.is_none() => text_xml(&run.text),
+            runs => runs_xml(runs),
+        }
+    }
+
+    // the box sits right of the cell, starting a row above it, or over the last column
+    fn anchor(
+        &self,
+        row: u32,
+        col: u32,
+        col_width: impl Fn(u32) -> u32,
+        row_height: impl Fn(u32) -> u32,
+    ) -> Anchor {
+        let (top_row, y_offset) = if row == 0 { (0, 2) } else { (row - 1, 10) };
+        let size = (
+            (self.width * 4.0 / 3.0).round() as u32,
+            (self.height * 4.0 / 3.0).round() as u32,
+        );
+        Anchor::new(
+            (top_row, (col + 1).min(MAX_COLS - 1)),
+            (15, y_offset),
+            size,
+            col_width,
+            row_height,
+        )
+    }
+}
+
+/// `xl/commentsN.xml` for the notes of one sheet, keyed by 0-based (row, col).
+pub(crate) fn comments_xml(notes: &BTreeMap<(u32, u32), Note>) -> String {
+    let mut authors: Vec<&str> = vec![];
+    let mut list = String::new();
+    for ((row, col), note) in notes {
+        let author = note.author.as_deref().unwrap_or("");
+        let author_id = match authors.iter().position(|a| *a == author) {
+            Some(i) => i,
+            None => {
+                authors.push(author);
+                authors.len() - 1
+            }
+        };
+        list.push_str(&format!(
+            "<comment ref=\"{}\" authorId=\"{author_id}\"><text>{}</text></comment>",
+            make_cell_ref(row + 1, *col),
+            note.text_xml()
+        ));
+    }
+
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+        r#"<comments xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><authors>"#,
+    ));
+    for author in authors {
+        xml.push_str(&format!("<author>{}</author>", xml_escape(author)));
+    }
+    xml.push_str(&format!(
+        "</authors><commentList>{list}</commentList></comments>"
+    ));
+    xml
+}
+
+/// Number of blocks of 1024 VML shape ids taken by `count` notes, numbered from 1
+/// within the first block.
+pub(crate) fn id_blocks(count: usize) -> usize {
+    count / 1024 + 1
+}
+
+/// `xl/drawings/vmlDrawingN.vml` with the note boxes of one sheet, given the pixel
+/// size of each column and row. The shape ids come from [`id_blocks`] blocks
+/// starting at `first_block`, which no other drawing in the workbook may share.
+pub(crate) fn vml_xml(
+    notes: &BTreeMap<(u32, u32), Note>,
+    first_block: usize,
+    col_width: impl Fn(u32) -> u32,
+    row_height: impl Fn(u32) -> u32,
+) -> String {
+    let mut xml = format!(
+        concat!(
+            r#"<xml xmlns:v="urn:schemas-microsoft-com:vml" xmlns:o="urn:schemas-microsoft-com:office:office" xmlns:x="urn:schemas-microsoft-com:office:excel">"#,
+            r#"<o:shapelayout v:ext="edit"><o:idmap v:ext="edit" data="{}"/></o:shapelayout>"#,
+            r#"<v:shapetype id="_x0000_t202" coordsize="21600,21600" o:spt="202" path="m,l,21600r21600,l21600,xe">"#,
+            r#"<v:stroke joinstyle="miter"/><v:path gradientshapeok="t" o:connecttype="rect"/></v:shapetype>"#,
+        ),
+        (first_block..first_block + id_blocks(notes.len()))
+            .map(|block| block.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    for (i, ((row, col), note)) in notes.iter().enumerate() {
+        let anchor = note.anchor(*row, *col, &col_width, &row_height);
+        let (x, y) = anchor.position();
+        let (margin_left, margin_top) = (x as f64 * 0.75, y as f64 * 0.75);
+        let visibility = if note.visible { "visible" } else { "hidden" };
+        xml.push_str(&format!(
+            concat!(
+                r##"<v:shape id="_x0000_s{}" type="#_x0000_t202" "##,
+                r#"style="position:absolute;margin-left:{}pt;margin-top:{}pt;width:{}pt;height:{}pt;z-index:{};visibility:{}" "#,
+                r##"fillcolor="#ffffe1" o:insetmode="auto">"##,
+                r##"<v:fill color2="#ffffe1"/><v:shadow on="t" color="black" obscured="t"/><v:path o:connecttype="none"/>"##,
+                r#"<v:textbox style="mso-direction-alt:auto"><div style="text-align:left"></div></v:textbox>"#,
+                r#"<x:ClientData ObjectType="Note"><x:MoveWithCells/><x:SizeWithCells/>"#,
+                r#"<x:Anchor>{}</x:Anchor><x:AutoFill>False</x:AutoFill><x:Row>{}</x:Row><x:Column>{}</x:Column>"#,
+            ),
+            first_block * 1024 + i + 1,
+            margin_left,
+            margin_top,
+            note.width,
+            note.height,
+            i + 1,
+            visibility,
+            anchor.corners().map(|n| n.to_string()).join(", "),
+            row,
+            col
+        ));
+        if note.visible {
+            xml.push_str("<x:Visible/>");
+        }
+        xml.push_str("</x:ClientData></v:shape>");
+    }
+
+    xml.push_str("</xml>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_follows_cell_sizes() {
+        let note = Note::new("x");
+        let anchor = note.anchor(1, 1, |_| 64, |_| 20);
+        assert_eq!(anchor.corners(), [2, 15, 0, 10, 4, 15, 4, 4]);
+        assert_eq!(anchor.position(), (143, 10));
+
+        let wide = |c| if c == 2 { 200 } else { 64 };
+        let tall = |r| if r == 0 { 100 } else { 20 };
+        let anchor = note.anchor(1, 1, wide, tall);
+        assert_eq!(anchor.corners(), [2, 15, 0, 10, 2, 143, 0, 84]);
+        assert_eq!(anchor.position(), (143, 10));
+
+        let anchor = note.anchor(0, 0, |c| if c == 1 { 0 } else { 64 }, |_| 20);
+        assert_eq!(anchor.corners()[..4], [2, 15, 0, 2]);
+        assert_eq!(anchor.position(), (79, 2));
+    }
+
+    #[test]
+    fn vml_uses_sheet_positions() {
+        let notes = BTreeMap::from([((1, 1), Note::new("x"))]);
+        let xml = vml_xml(&notes, 1, |_| 100, |_| 20);
+        assert!(
+            xml.contains("margin-left:161.25pt;margin-top:7.5pt;"),
+            "{xml}"
+        );
+        assert!(
+            xml.contains("<x:Anchor>2, 15, 0, 10, 3, 43, 4, 4</x:Anchor>"),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn vml_ids_span_blocks() {
+        let notes: BTreeMap<_, _> = (0..1500).map(|r| ((r, 0), Note::new("x"))).collect();
+        assert_eq!(id_blocks(notes.len()), 2);
+        let xml = vml_xml(&notes, 3, |_| 64, |_| 20);
+        assert!(xml.contains(r#"<o:idmap v:ext="edit" data="3,4"/>"#));
+        assert!(xml.contains(r#"<v:shape id="_x0000_s3073" "#));
+        assert!(xml.contains(r#"<v:shape id="_x0000_s4572" "#));
+
+        assert_eq!(id_blocks(1023), 1);
+        assert_eq!(id_blocks(1024), 2);
+    }
+}
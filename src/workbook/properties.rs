@@ -0,0 +1,303 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Result, error::MrXlsxError, workbook::xml_escape};
+
+/// Value of a custom document property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CustomProperty {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(SystemTime),
+}
+
+impl From<&str> for CustomProperty {
+    fn from(v: &str) -> Self {
+        CustomProperty::Text(v.to_string())
+    }
+}
+
+impl From<String> for CustomProperty {
+    fn from(v: String) -> Self {
+        CustomProperty::Text(v)
+    }
+}
+
+impl From<f64> for CustomProperty {
+    fn from(v: f64) -> Self {
+        CustomProperty::Number(v)
+    }
+}
+
+impl From<bool> for CustomProperty {
+    fn from(v: bool) -> Self {
+        CustomProperty::Bool(v)
+    }
+}
+
+impl From<SystemTime> for CustomProperty {
+    fn from(v: SystemTime) -> Self {
+        CustomProperty::Date(v)
+    }
+}
+
+/// Metadata written to `docProps/core.xml`, `docProps/app.xml` and `docProps/custom.xml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocProperties {
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub author: Option<String>,
+    pub manager: Option<String>,
+    pub company: Option<String>,
+    pub category: Option<String>,
+    pub keywords: Option<String>,
+    pub comments: Option<String>,
+    pub status: Option<String>,
+    /// Defaults to the time the workbook is finished.
+    pub created: Option<SystemTime>,
+    /// Defaults to `created`.
+    pub modified: Option<SystemTime>,
+    pub custom: Vec<(String, CustomProperty)>,
+}
+
+impl DocProperties {
+    pub fn new() -> Self {
+        DocProperties::default()
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    pub fn subject<S: Into<String>>(mut self, subject: S) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+    pub fn author<S: Into<String>>(mut self, author: S) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+    pub fn manager<S: Into<String>>(mut self, manager: S) -> Self {
+        self.manager = Some(manager.into());
+        self
+    }
+    pub fn company<S: Into<String>>(mut self, company: S) -> Self {
+        self.company = Some(company.into());
+        self
+    }
+    pub fn category<S: Into<String>>(mut self, category: S) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+    pub fn keywords<S: Into<String>>(mut self, keywords: S) -> Self {
+        self.keywords = Some(keywords.into());
+        self
+    }
+    pub fn comments<S: Into<String>>(mut self, comments: S) -> Self {
+        self.comments = Some(comments.into());
+        self
+    }
+    pub fn status<S: Into<String>>(mut self, status: S) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+    pub fn created(mut self, time: SystemTime) -> Self {
+        self.created = Some(time);
+        self
+    }
+    pub fn modified(mut self, time: SystemTime) -> Self {
+        self.modified = Some(time);
+        self
+    }
+
+    /// Set a custom property, replacing any property of the same name.
+    pub fn custom<S: Into<String>, V: Into<CustomProperty>>(mut self, name: S, value: V) -> Self {
+        let name = name.into();
+        let value = value.into();
+        match self.custom.iter_mut().find(|(n, _)| *n == name) {
+            Some(existing) => existing.1 = value,
+            None => self.custom.push((name, value)),
+        }
+        self
+    }
+
+    // custom.xml has no representation for NaN or infinity
+    pub(crate) fn validate(&self) -> Result<()> {
+        for (name, value) in &self.custom {
+            if let CustomProperty::Number(n) = value
+                && !n.is_finite()
+            {
+                return Err(MrXlsxError::InvalidArgument(format!(
+                    "custom property '{name}' is {n}, which is not a number Excel can store"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn core_xml(&self, now: SystemTime) -> String {
+        let created = self.created.unwrap_or(now);
+        let modified = self.modified.unwrap_or(created);
+
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" "#,
+            r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" "#,
+            r#"xmlns:dcmitype="http://purl.org/dc/dcmitype/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+        ));
+        let elements = [
+            ("dc:title", &self.title),
+            ("dc:subject", &self.subject),
+            ("dc:creator", &self.author),
+            ("cp:keywords", &self.keywords),
+            ("dc:description", &self.comments),
+            ("cp:lastModifiedBy", &self.author),
+        ];
+        for (tag, value) in elements {
+            if let Some(value) = value {
+                xml.push_str(&format!("<{tag}>{}</{tag}>", xml_escape(value)));
+            }
+        }
+        xml.push_str(&format!(
+            concat!(
+                r#"<dcterms:created xsi:type="dcterms:W3CDTF">{}</dcterms:created>"#,
+                r#"<dcterms:modified xsi:type="dcterms:W3CDTF">{}</dcterms:modified>"#,
+            ),
+            w3c_datetime(created),
+            w3c_datetime(modified)
+        ));
+        for (tag, value) in [
+            ("cp:category", &self.category),
+            ("cp:contentStatus", &self.status),
+        ] {
+            if let Some(value) = value {
+                xml.push_str(&format!("<{tag}>{}</{tag}>", xml_escape(value)));
+            }
+        }
+        xml.push_str("</cp:coreProperties>");
+        xml
+    }
+
+    /// `docProps/app.xml`, listing the worksheet and chartsheet names.
+    pub(crate) fn app_xml(&self, worksheets: &[&str], chartsheets: &[&str]) -> String {
+        let mut pairs = vec![];
+        if !worksheets.is_empty() {
+            pairs.push(("Worksheets", worksheets.len()));
+        }
+        if !chartsheets.is_empty() {
+            pairs.push(("Charts", chartsheets.len()));
+        }
+        let mut heading_pairs = String::new();
+        for (heading, count) in &pairs {
+            heading_pairs.push_str(&format!(
+                "<vt:variant><vt:lpstr>{heading}</vt:lpstr></vt:variant><vt:variant><vt:i4>{count}</vt:i4></vt:variant>"
+            ));
+        }
+        let mut titles = String::new();
+        for name in worksheets.iter().chain(chartsheets) {
+            titles.push_str(&format!("<vt:lpstr>{}</vt:lpstr>", xml_escape(name)));
+        }
+
+        let mut xml = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" "#,
+                r#"xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">"#,
+                r#"<Application>Microsoft Excel</Application><DocSecurity>0</DocSecurity><ScaleCrop>false</ScaleCrop>"#,
+                r#"<HeadingPairs><vt:vector size="{}" baseType="variant">{}</vt:vector></HeadingPairs>"#,
+                r#"<TitlesOfParts><vt:vector size="{}" baseType="lpstr">{}</vt:vector></TitlesOfParts>"#,
+            ),
+            pairs.len() * 2,
+            heading_pairs,
+            worksheets.len() + chartsheets.len(),
+            titles
+        );
+        for (tag, value) in [("Manager", &self.manager), ("Company", &self.company)] {
+            if let Some(value) = value {
+                xml.push_str(&format!("<{tag}>{}</{tag}>", xml_escape(value)));
+            }
+        }
+        xml.push_str(concat!(
+            "<LinksUpToDate>false</LinksUpToDate><SharedDoc>false</SharedDoc>",
+            "<HyperlinksChanged>false</HyperlinksChanged><AppVersion>12.0000</AppVersion></Properties>",
+        ));
+        xml
+    }
+
+    pub(crate) fn custom_xml(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" "#,
+            r#"xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">"#,
+        ));
+        // property ids start at 2
+        for (i, (name, value)) in self.custom.iter().enumerate() {
+            let value = match value {
+                CustomProperty::Text(text) => {
+                    format!("<vt:lpwstr>{}</vt:lpwstr>", xml_escape(text))
+                }
+                CustomProperty::Number(n) => format!("<vt:r8>{n}</vt:r8>"),
+                CustomProperty::Bool(b) => format!("<vt:bool>{b}</vt:bool>"),
+                CustomProperty::Date(t) => {
+                    format!("<vt:filetime>{}</vt:filetime>", w3c_datetime(*t))
+                }
+            };
+            xml.push_str(&format!(
+                r#"<property fmtid="{{D5CDD505-2E9C-101B-9397-08002B2CF9AE}}" pid="{}" name="{}">{}</property>"#,
+                i + 2,
+                xml_escape(name),
+                value
+            ));
+        }
+        xml.push_str("</Properties>");
+        xml
+    }
+}
+
+/// UTC timestamp such as `2024-03-01T09:30:00Z`.
+fn w3c_datetime(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// proleptic Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_custom_numbers_are_rejected() {
+        let props = DocProperties::new().custom("Score", 1.5);
+        assert!(props.validate().is_ok());
+        assert!(props.custom_xml().contains("<vt:r8>1.5</vt:r8>"));
+        for n in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                props.clone().custom("Score", n).validate(),
+                Err(MrXlsxError::InvalidArgument(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{col_to_letters, make_cell_ref},
+};
+
+/// Rows and columns of a worksheet.
+pub(crate) const MAX_ROWS: u32 = 1_048_576;
+pub(crate) const MAX_COLS: u32 = 16_384;
+
+/// Reject a 0-based cell position outside the worksheet.
+pub(crate) fn check_cell(row: u32, col: u32) -> Result<()> {
+    if row >= MAX_ROWS || col >= MAX_COLS {
+        return Err(MrXlsxError::InvalidRange(format!(
+            "row {row}, column {col} is outside the sheet ({MAX_ROWS} rows, {MAX_COLS} columns)"
+        )));
+    }
+    Ok(())
+}
+
+/// Inclusive block of cells, 0-based rows and columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct CellRange {
+    pub(crate) first_row: u32,
+    pub(crate) first_col: u32,
+    pub(crate) last_row: u32,
+    pub(crate) last_col: u32,
+}
+
+impl CellRange {
+    pub(crate) fn new(
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+    ) -> Result<Self> {
+        check_cell(first_row, first_col)?;
+        check_cell(last_row, last_col)?;
+        if first_row > last_row || first_col > last_col {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "{}:{} ends before it starts",
+                make_cell_ref(first_row + 1, first_col),
+                make_cell_ref(last_row + 1, last_col)
+            )));
+        }
+        Ok(CellRange {
+            first_row,
+            first_col,
+            last_row,
+            last_col,
+        })
+    }
+
+    pub(crate) fn is_single_cell(self) -> bool {
+        self.first_row == self.last_row && self.first_col == self.last_col
+    }
+
+    pub(crate) fn overlaps(&self, other: &CellRange) -> bool {
+        self.first_row <= other.last_row
+            && other.first_row <= self.last_row
+            && self.first_col <= other.last_col
+            && other.first_col <= self.last_col
+    }
+
+    /// e.g. "A1:C3", or "A1" for a single cell.
+    pub(crate) fn to_ref(self) -> String {
+        let first = make_cell_ref(self.first_row + 1, self.first_col);
+        if self.is_single_cell() {
+            return first;
+        }
+        format!(
+            "{first}:{}",
+            make_cell_ref(self.last_row + 1, self.last_col)
+        )
+    }
+
+    /// e.g. "$A$1:$C$3".
+    pub(crate) fn to_absolute_ref(self) -> String {
+        let first = format!("${}${}", col_to_letters(self.first_col), self.first_row + 1);
+        if self.is_single_cell() {
+            return first;
+        }
+        format!(
+            "{first}:${}${}",
+            col_to_letters(self.last_col),
+            self.last_row + 1
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_rejects_cells_outside_the_sheet() {
+        assert!(CellRange::new(0, 0, MAX_ROWS - 1, MAX_COLS - 1).is_ok());
+        assert!(matches!(
+            CellRange::new(0, 0, MAX_ROWS, 0),
+            Err(MrXlsxError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            CellRange::new(0, 0, 0, MAX_COLS),
+            Err(MrXlsxError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            CellRange::new(u32::MAX, 0, u32::MAX, 0),
+            Err(MrXlsxError::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn new_rejects_reversed_ranges() {
+        assert!(matches!(
+            CellRange::new(2, 0, 1, 0),
+            Err(MrXlsxError::InvalidRange(_))
+        ));
+        assert_eq!(CellRange::new(0, 0, 2, 2).unwrap().to_ref(), "A1:C3");
+    }
+}
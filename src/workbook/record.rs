@@ -0,0 +1,528 @@
+use std::{collections::HashSet, fmt::Display};
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{Result, error::MrXlsxError, workbook::cell::CellValue};
+
+impl ser::Error for MrXlsxError {
+    fn custom<T: Display>(msg: T) -> Self {
+        MrXlsxError::InvalidArgument(msg.to_string())
+    }
+}
+
+// integers above 2^53 lose digits as f64, so they are written as text
+const MAX_EXACT_INTEGER: u128 = 1 << 53;
+
+/// A field of a record.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecordCell {
+    pub(crate) name: String,
+    pub(crate) value: CellValue,
+    // written for `None`, which may stand for a nested struct of several columns
+    pub(crate) none: bool,
+}
+
+/// Named cells of a record. Fields of nested structs and maps are flattened with
+/// the parent field as prefix, e.g. `address.city`.
+pub(crate) fn record_cells<T: Serialize + ?Sized>(record: &T) -> Result<Vec<RecordCell>> {
+    let mut cells = vec![];
+    record.serialize(FieldSerializer {
+        cells: &mut cells,
+        name: String::new(),
+    })?;
+    if cells.iter().any(|cell| cell.name.is_empty()) {
+        return Err(MrXlsxError::InvalidArgument(
+            "Records must be structs or maps".into(),
+        ));
+    }
+    Ok(cells)
+}
+
+/// Column names covering the fields of every record, in field order. A field that
+/// is `None` in some records and a flattened struct in others gets the struct's
+/// columns only.
+pub(crate) fn record_header(records: &[Vec<RecordCell>]) -> Vec<String> {
+    let mut header: Vec<String> = vec![];
+    let mut known: HashSet<&str> = HashSet::new();
+    // names seen with a value other than `None`
+    let mut valued: HashSet<&str> = HashSet::new();
+    for record in records {
+        valued.extend(record.iter().filter(|c| !c.none).map(|c| c.name.as_str()));
+        if record.iter().all(|c| known.contains(c.name.as_str())) {
+            continue;
+        }
+        // new columns go right after the previous field of the record
+        let mut next = 0;
+        for cell in record {
+            let name = cell.name.as_str();
+            if known.contains(name) {
+                next = header.iter().position(|h| h == name).unwrap_or(next) + 1;
+                continue;
+            }
+            if cell.none
+                && let Some(last) = header.iter().rposition(|h| is_subfield(h, name))
+            {
+                next = last + 1;
+                continue;
+            }
+            header.insert(next, cell.name.clone());
+            known.insert(name);
+            next += 1;
+        }
+    }
+    header.retain(|h| valued.contains(h.as_str()) || !known.iter().any(|k| is_subfield(k, h)));
+    header
+}
+
+/// Values of `record` in the column order of `header`, blank where a field is missing.
+pub(crate) fn record_row(header: &[String], record: Vec<RecordCell>) -> Result<Vec<CellValue>> {
+    let mut row = vec![CellValue::Blank; header.len()];
+    for cell in record {
+        match header.iter().position(|h| *h == cell.name) {
+            Some(col) => row[col] = cell.value,
+            // a nested struct left as `None`
+            None if cell.none && header.iter().any(|h| is_subfield(h, &cell.name)) => {}
+            None => {
+                return Err(MrXlsxError::InvalidArgument(format!(
+                    "Field '{}' is not in the header row",
+                    cell.name
+                )));
+            }
+        }
+    }
+    Ok(row)
+}
+
+// whether `name` is a flattened field of `parent`, e.g. `address.city` of `address`
+fn is_subfield(name: &str, parent: &str) -> bool {
+    name.strip_prefix(parent)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn unsupported(kind: &str, name: &str) -> MrXlsxError {
+    MrXlsxError::InvalidArgument(format!(
+        "Field '{name}' is a {kind}, which cannot be written to a cell"
+    ))
+}
+
+struct FieldSerializer<'a> {
+    cells: &'a mut Vec<RecordCell>,
+    name: String,
+}
+
+impl<'a> FieldSerializer<'a> {
+    fn push(self, value: CellValue) -> Result<()> {
+        self.cells.push(RecordCell {
+            name: self.name,
+            value,
+            none: false,
+        });
+        Ok(())
+    }
+
+    fn integer(self, v: i128) -> Result<()> {
+        if v.unsigned_abs() <= MAX_EXACT_INTEGER {
+            self.push(CellValue::Number(v as f64))
+        } else {
+            self.push(CellValue::Text(v.to_string()))
+        }
+    }
+
+    fn fields(self) -> Fields<'a> {
+        Fields {
+            cells: self.cells,
+            prefix: self.name,
+            key: None,
+        }
+    }
+}
+
+impl<'a> ser::Serializer for FieldSerializer<'a> {
+    type Ok = ();
+    type Error = MrXlsxError;
+    type SerializeSeq = Impossible<(), MrXlsxError>;
+    type SerializeTuple = Impossible<(), MrXlsxError>;
+    type SerializeTupleStruct = Impossible<(), MrXlsxError>;
+    type SerializeTupleVariant = Impossible<(), MrXlsxError>;
+    type SerializeMap = Fields<'a>;
+    type SerializeStruct = Fields<'a>;
+    type SerializeStructVariant = Fields<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.push(CellValue::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.integer(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.integer(v as i128)
+    }
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        match i128::try_from(v) {
+            Ok(v) => self.integer(v),
+            Err(_) => self.push(CellValue::Text(v.to_string())),
+        }
+    }
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        // through the shortest decimal, so 0.1f32 stays 0.1 rather than 0.10000000149011612
+        let n = v.to_string().parse().unwrap_or(v as f64);
+        self.push(CellValue::Number(n))
+    }
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.push(CellValue::Number(v))
+    }
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.push(CellValue::Text(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.push(CellValue::Text(v.to_string()))
+    }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(unsupported("byte array", &self.name))
+    }
+    fn serialize_none(self) -> Result<()> {
+        self.cells.push(RecordCell {
+            name: self.name,
+            value: CellValue::Blank,
+            none: true,
+        });
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<()> {
+        self.push(CellValue::Blank)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.push(CellValue::Blank)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.push(CellValue::Text(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(unsupported("sequence", &self.name))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(unsupported("tuple", &self.name))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(unsupported("tuple struct", &self.name))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported("tuple variant", &self.name))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(self.fields())
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self.fields())
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(self.fields())
+    }
+}
+
+/// Fields of a struct or entries of a map, named after `prefix`.
+struct Fields<'a> {
+    cells: &'a mut Vec<RecordCell>,
+    prefix: String,
+    // map key waiting for its value
+    key: Option<String>,
+}
+
+impl Fields<'_> {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        let name = if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.prefix)
+        };
+        value.serialize(FieldSerializer {
+            cells: self.cells,
+            name,
+        })
+    }
+}
+
+impl ser::SerializeStruct for Fields<'_> {
+    type Ok = ();
+    type Error = MrXlsxError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Fields<'_> {
+    type Ok = ();
+    type Error = MrXlsxError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Fields<'_> {
+    type Ok = ();
+    type Error = MrXlsxError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        let mut cells = vec![];
+        key.serialize(FieldSerializer {
+            cells: &mut cells,
+            name: String::new(),
+        })?;
+        let key = match cells.pop().map(|cell| cell.value) {
+            Some(CellValue::Text(text)) if cells.is_empty() => text,
+            Some(CellValue::Number(n)) if cells.is_empty() => n.to_string(),
+            _ => {
+                return Err(MrXlsxError::InvalidArgument(
+                    "Map keys must be strings or numbers".into(),
+                ));
+            }
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().unwrap_or_default();
+        self.field(&key, value)
+    }
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Address {
+        city: &'static str,
+        zip: Option<u32>,
+    }
+
+    #[derive(Serialize)]
+    struct Customer {
+        id: u64,
+        name: &'static str,
+        address: Option<Address>,
+        active: bool,
+    }
+
+    fn customer(id: u64, address: Option<Address>) -> Customer {
+        Customer {
+            id,
+            name: "Ann",
+            address,
+            active: true,
+        }
+    }
+
+    fn names(cells: &[RecordCell]) -> Vec<&str> {
+        cells.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn nested_structs_are_flattened() {
+        let address = Address {
+            city: "Oslo",
+            zip: None,
+        };
+        let cells = record_cells(&customer(7, Some(address))).unwrap();
+        assert_eq!(
+            names(&cells),
+            ["id", "name", "address.city", "address.zip", "active"]
+        );
+        assert_eq!(cells[0].value, CellValue::Number(7.0));
+        assert_eq!(cells[2].value, CellValue::Text("Oslo".into()));
+        assert_eq!(cells[3].value, CellValue::Blank);
+        assert_eq!(cells[4].value, CellValue::Bool(true));
+    }
+
+    #[test]
+    fn none_nested_struct_takes_the_columns_of_other_records() {
+        let records = vec![
+            record_cells(&customer(1, None)).unwrap(),
+            record_cells(&customer(
+                2,
+                Some(Address {
+                    city: "Rome",
+                    zip: Some(100),
+                }),
+            ))
+            .unwrap(),
+        ];
+        let header = record_header(&records);
+        assert_eq!(
+            header,
+            ["id", "name", "address.city", "address.zip", "active"]
+        );
+
+        let mut rows = records.into_iter().map(|r| record_row(&header, r).unwrap());
+        assert_eq!(
+            rows.next().unwrap(),
+            [
+                CellValue::Number(1.0),
+                CellValue::Text("Ann".into()),
+                CellValue::Blank,
+                CellValue::Blank,
+                CellValue::Bool(true),
+            ]
+        );
+        assert_eq!(
+            rows.next().unwrap(),
+            [
+                CellValue::Number(2.0),
+                CellValue::Text("Ann".into()),
+                CellValue::Text("Rome".into()),
+                CellValue::Number(100.0),
+                CellValue::Bool(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn none_after_header_from_some_writes_blanks() {
+        let first = record_cells(&customer(
+            1,
+            Some(Address {
+                city: "Rome",
+                zip: None,
+            }),
+        ))
+        .unwrap();
+        let header = record_header(&[first]);
+        let row = record_row(&header, record_cells(&customer(2, None)).unwrap()).unwrap();
+        assert_eq!(row.len(), 5);
+        assert_eq!(row[2], CellValue::Blank);
+        assert_eq!(row[3], CellValue::Blank);
+    }
+
+    #[test]
+    fn header_keeps_field_order_when_all_nested_are_none() {
+        let records = vec![
+            record_cells(&customer(1, None)).unwrap(),
+            record_cells(&customer(2, None)).unwrap(),
+        ];
+        assert_eq!(record_header(&records), ["id", "name", "address", "active"]);
+    }
+
+    #[test]
+    fn unknown_field_is_rejected() {
+        let header = vec!["id".to_string()];
+        let record = record_cells(&customer(1, None)).unwrap();
+        assert!(record_row(&header, record).is_err());
+    }
+
+    #[test]
+    fn large_integers_are_written_as_text() {
+        #[derive(Serialize)]
+        struct Ids {
+            small: u64,
+            exact: i64,
+            large: u64,
+            negative: i64,
+        }
+        let cells = record_cells(&Ids {
+            small: 42,
+            exact: 1 << 53,
+            large: (1 << 53) + 1,
+            negative: -(1 << 60),
+        })
+        .unwrap();
+        assert_eq!(cells[0].value, CellValue::Number(42.0));
+        assert_eq!(cells[1].value, CellValue::Number(9007199254740992.0));
+        assert_eq!(cells[2].value, CellValue::Text("9007199254740993".into()));
+        assert_eq!(
+            cells[3].value,
+            CellValue::Text("-1152921504606846976".into())
+        );
+    }
+
+    #[test]
+    fn sequences_and_non_records_are_rejected() {
+        #[derive(Serialize)]
+        struct Tagged {
+            tags: Vec<u8>,
+        }
+        assert!(record_cells(&Tagged { tags: vec![1] }).is_err());
+        assert!(record_cells(&5).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::workbook::xml_escape;
+
+pub(crate) const REL_WORKSHEET: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+pub(crate) const REL_CHARTSHEET: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chartsheet";
+pub(crate) const REL_CHART: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chart";
+pub(crate) const REL_HYPERLINK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+pub(crate) const REL_TABLE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/table";
+pub(crate) const REL_DRAWING: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing";
+pub(crate) const REL_IMAGE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+pub(crate) const REL_COMMENTS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments";
+pub(crate) const REL_VML_DRAWING: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/vmlDrawing";
+pub(crate) const REL_OFFICE_DOCUMENT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+pub(crate) const REL_CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+pub(crate) const REL_EXTENDED_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+pub(crate) const REL_CUSTOM_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+
+/// Relationship part of a single package part, e.g. `xl/worksheets/_rels/sheet1.xml.rels`.
+#[derive(Default)]
+pub(crate) struct Relationships {
+    items: Vec<(String, String, bool)>,
+}
+
+impl Relationships {
+    /// Add a relationship and return its id.
+    pub(crate) fn add(&mut self, rel_type: &str, target: &str, external: bool) -> String {
+        self.items
+            .push((rel_type.to_string(), target.to_string(), external));
+        format!("rId{}", self.items.len())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub(crate) fn to_xml(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        ));
+        for (i, (rel_type, target, external)) in self.items.iter().enumerate() {
+            let target = xml_escape(target);
+            xml.push_str(&format!(
+                r#"<Relationship Id="rId{}" Type="{rel_type}" Target="{target}""#,
+                i + 1
+            ));
+            if *external {
+                xml.push_str(r#" TargetMode="External""#);
+            }
+            xml.push_str("/>");
+        }
+        xml.push_str("</Relationships>");
+        xml
+    }
+}
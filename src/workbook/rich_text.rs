@@ -0,0 +1,43 @@
+use crate::workbook::{
+    shared_strings::text_xml,
+    style::{Style, font::Font},
+};
+
+/// A run of text with its own font.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    /// `None` keeps the font of the cell or note.
+    pub font: Option<Font>,
+}
+
+impl TextRun {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        TextRun {
+            text: text.into(),
+            font: None,
+        }
+    }
+
+    /// Run using the font of `style`, e.g. `TextRun::styled("Total", &Style::new().bold())`.
+    pub fn styled<S: Into<String>>(text: S, style: &Style) -> Self {
+        TextRun {
+            text: text.into(),
+            font: Some(style.font.clone()),
+        }
+    }
+}
+
+/// `<r>` elements of a rich text string.
+pub(crate) fn runs_xml(runs: &[TextRun]) -> String {
+    let mut xml = String::new();
+    for run in runs {
+        xml.push_str("<r>");
+        if let Some(font) = &run.font {
+            xml.push_str(&font.to_rpr_xml());
+        }
+        xml.push_str(&text_xml(&run.text));
+        xml.push_str("</r>");
+    }
+    xml
+}
@@ -0,0 +1,101 @@
+use crate::workbook::style::Style;
+
+pub(crate) const MAX_ROW_HEIGHT: f64 = 409.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowOptions {
+    pub height: Option<f64>,
+    pub hidden: bool,
+    pub outline_level: u8,
+    pub collapsed: bool,
+    /// Format of the empty cells of the row.
+    pub style: Option<Style>,
+}
+
+impl RowOptions {
+    pub fn new() -> Self {
+        RowOptions::default()
+    }
+
+    /// Height in points. A NaN or infinite height leaves the row at its default
+    /// height.
+    pub fn height(mut self, height: f64) -> Self {
+        self.height = height
+            .is_finite()
+            .then(|| height.clamp(0.0, MAX_ROW_HEIGHT));
+        self
+    }
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+    /// Outline (grouping) level, 1-7.
+    pub fn outline_level(mut self, level: u8) -> Self {
+        self.outline_level = level.min(7);
+        self
+    }
+    pub fn collapsed(mut self) -> Self {
+        self.collapsed = true;
+        self
+    }
+    pub fn style(mut self, style: &Style) -> Self {
+        self.style = Some(style.clone());
+        self
+    }
+
+    // the height set by the caller, if it can be written
+    fn custom_height(&self) -> Option<f64> {
+        self.height.filter(|height| height.is_finite())
+    }
+
+    /// Attributes of `<row>` after `r`, given the registered index of `style`.
+    pub(crate) fn to_attrs(&self, style_idx: Option<usize>) -> String {
+        let mut attrs = String::new();
+        if let Some(idx) = style_idx {
+            attrs.push_str(&format!(" s=\"{idx}\" customFormat=\"1\""));
+        }
+        if let Some(height) = self.custom_height() {
+            attrs.push_str(&format!(" ht=\"{height}\" customHeight=\"1\""));
+        }
+        if self.hidden {
+            attrs.push_str(" hidden=\"1\"");
+        }
+        if self.outline_level > 0 {
+            attrs.push_str(&format!(" outlineLevel=\"{}\"", self.outline_level));
+        }
+        if self.collapsed {
+            attrs.push_str(" collapsed=\"1\"");
+        }
+        attrs
+    }
+
+    /// Displayed height in pixels, `None` for the default height.
+    pub(crate) fn pixels(&self) -> Option<u32> {
+        if self.hidden {
+            return Some(0);
+        }
+        self.custom_height()
+            .map(|height| (height * 4.0 / 3.0).round() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_heights_are_ignored() {
+        for height in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(RowOptions::new().height(height).height, None);
+            let options = RowOptions {
+                height: Some(height),
+                ..RowOptions::default()
+            };
+            assert_eq!(options.to_attrs(None), "");
+            assert_eq!(options.pixels(), None);
+        }
+        let options = RowOptions::new().height(500.0);
+        assert_eq!(options.to_attrs(None), r#" ht="409" customHeight="1""#);
+        assert_eq!(options.pixels(), Some(545));
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Seek, SeekFrom, Write},
+};
+
+use tempfile::NamedTempFile;
+
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{
+        rich_text::{TextRun, runs_xml},
+        xml_escape,
+    },
+};
+
+pub(crate) const DEFAULT_SHARED_STRINGS_CAPACITY: usize = 100_000;
+
+/// Interned string table backing `xl/sharedStrings.xml`.
+///
+/// The `<si>` entries are streamed to a temp file as they are interned, so only
+/// the dedup index lives in memory. Once the index holds `capacity` strings, new
+/// strings are still appended to the table but are no longer deduplicated.
+pub(crate) struct SharedStringTable {
+    temp: BufWriter<NamedTempFile>,
+    index: HashMap<String, u32>,
+    capacity: usize,
+    count: u64,
+    unique_count: u32,
+}
+
+impl SharedStringTable {
+    pub(crate) fn new(capacity: usize) -> Result<Self> {
+        Ok(SharedStringTable {
+            temp: BufWriter::new(NamedTempFile::new()?),
+            index: HashMap::new(),
+            capacity,
+            count: 0,
+            unique_count: 0,
+        })
+    }
+
+    pub(crate) fn intern(&mut self, text: &str) -> Result<u32> {
+        self.intern_si(text_xml(text))
+    }
+
+    pub(crate) fn intern_rich(&mut self, runs: &[TextRun]) -> Result<u32> {
+        self.intern_si(runs_xml(runs))
+    }
+
+    // strings are deduplicated by their `<si>` content, so plain and rich
+    // strings never share an entry
+    fn intern_si(&mut self, si: String) -> Result<u32> {
+        if let Some(&i) = self.index.get(&si) {
+            self.count += 1;
+            return Ok(i);
+        }
+
+        let i = self.unique_count;
+        self.unique_count = i.checked_add(1).ok_or_else(|| {
+            MrXlsxError::InvalidArgument(format!("shared string table is full at {i} strings"))
+        })?;
+        self.count += 1;
+        write!(self.temp, "<si>{si}</si>")?;
+
+        if self.index.len() < self.capacity {
+            self.index.insert(si, i);
+        }
+        Ok(i)
+    }
+
+    pub(crate) fn write_xml<W: Write>(&mut self, w: &mut W) -> Result<()> {
+        write!(
+            w,
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<sst xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" count="{}" uniqueCount="{}">"#,
+            ),
+            self.count, self.unique_count
+        )?;
+
+        self.temp.flush()?;
+        let temp_file = self.temp.get_mut();
+        temp_file.seek(SeekFrom::Start(0))?;
+        std::io::copy(temp_file, w)?;
+
+        write!(w, "</sst>")?;
+        Ok(())
+    }
+}
+
+pub(crate) fn text_xml(text: &str) -> String {
+    let escaped = xml_escape(text);
+    if text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        format!("<t xml:space=\"preserve\">{escaped}</t>")
+    } else {
+        format!("<t>{escaped}</t>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sst_xml(sst: &mut SharedStringTable) -> String {
+        let mut out = Vec::new();
+        sst.write_xml(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn strings_are_deduplicated() {
+        let mut sst = SharedStringTable::new(DEFAULT_SHARED_STRINGS_CAPACITY).unwrap();
+        assert_eq!(sst.intern("a").unwrap(), 0);
+        assert_eq!(sst.intern(" b").unwrap(), 1);
+        assert_eq!(sst.intern("a").unwrap(), 0);
+        let xml = sst_xml(&mut sst);
+        assert!(xml.contains(r#"count="3" uniqueCount="2">"#), "{xml}");
+        assert!(
+            xml.ends_with(r#"<si><t>a</t></si><si><t xml:space="preserve"> b</t></si></sst>"#),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn strings_past_capacity_are_not_deduplicated() {
+        let mut sst = SharedStringTable::new(1).unwrap();
+        assert_eq!(sst.intern("a").unwrap(), 0);
+        assert_eq!(sst.intern("b").unwrap(), 1);
+        assert_eq!(sst.intern("b").unwrap(), 2);
+        assert_eq!(sst.intern("a").unwrap(), 0);
+        let xml = sst_xml(&mut sst);
+        assert!(xml.contains(r#"count="4" uniqueCount="3">"#), "{xml}");
+    }
+
+    #[test]
+    fn full_table_is_an_error() {
+        let mut sst = SharedStringTable::new(1).unwrap();
+        sst.unique_count = u32::MAX;
+        assert!(matches!(
+            sst.intern("a"),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        assert_eq!(sst.count, 0);
+    }
+}
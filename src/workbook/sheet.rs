@@ -1,43 +1,167 @@
-use std::io::{BufWriter, Write};
-use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use tempfile::NamedTempFile;
 
+#[cfg(feature = "serde")]
+use crate::workbook::record;
 use crate::{
     Result,
-    workbook::{cell::CellValue, make_cell_ref, style::{Style, StyleRegistry}, write_cell},
+    error::MrXlsxError,
+    workbook::{
+        PartIds,
+        cell::{CellValue, NonFinitePolicy},
+        chart::Chart,
+        column::{ColumnOptions, DEFAULT_COLUMN_WIDTH, auto_fit_width, width_to_pixels},
+        conditional::ConditionalFormat,
+        drawing::{Anchor, chart_frame_xml, drawing_xml, picture_xml},
+        filter::FilterCriteria,
+        formula::FormulaResult,
+        hyperlink::Hyperlink,
+        image::Image,
+        make_cell_ref,
+        note::{self, Note},
+        range::{self, CellRange},
+        rels::{
+            REL_CHART, REL_COMMENTS, REL_DRAWING, REL_HYPERLINK, REL_IMAGE, REL_TABLE,
+            REL_VML_DRAWING, Relationships,
+        },
+        row::RowOptions,
+        shared_strings::SharedStringTable,
+        style::{Style, StyleRegistry, number::NumberFormat},
+        table::{SheetTable, Table, TableRegistry},
+        validation::DataValidation,
+        view::{Pane, SheetView},
+        write_cell, xml_escape,
+    },
 };
 
 pub struct SheetWriter {
     name: String,
-    pub(crate) temp: BufWriter<NamedTempFile>,
+    temp: BufWriter<NamedTempFile>,
     current_row: u32,
     max_col: u32,
     style_reg: Arc<Mutex<StyleRegistry>>,
+    shared_strings: Option<Arc<Mutex<SharedStringTable>>>,
+    columns: BTreeMap<u32, ColumnOptions>,
+    // options of rows not written yet, keyed by 1-based row, with the registered style
+    row_options: BTreeMap<u32, (RowOptions, Option<usize>)>,
+    // custom pixel heights by 0-based row, kept for placing drawings
+    row_pixels: BTreeMap<u32, u32>,
+    outline_level_row: u8,
+    outline_summary: Option<(bool, bool)>,
+    auto_fit: bool,
+    col_chars: Vec<usize>,
+    view: SheetView,
+    // cells queued for rows not yet written, keyed by 1-based row then 0-based col
+    pending: BTreeMap<u32, BTreeMap<u32, PendingCell>>,
+    merges: Vec<CellRange>,
+    autofilter: Option<AutoFilter>,
+    hyperlinks: Vec<(String, Hyperlink)>,
+    hyperlink_style: Option<usize>,
+    // default formats of date and time cells, by `date_slot`
+    date_styles: [Option<usize>; 4],
+    date_1904: bool,
+    non_finite: NonFinitePolicy,
+    // set once a dynamic array formula is written
+    dynamic_arrays: bool,
+    // column names of the records written with `serialize`
+    #[cfg(feature = "serde")]
+    record_header: Option<Vec<String>>,
+    validations: Vec<(CellRange, DataValidation)>,
+    conditional_formats: Vec<(CellRange, ConditionalFormat, Option<usize>)>,
+    table_reg: Arc<Mutex<TableRegistry>>,
+    tables: Vec<SheetTable>,
+    print_area: Option<CellRange>,
+    print_title_rows: Option<(u32, u32)>,
+    print_title_cols: Option<(u32, u32)>,
+    notes: BTreeMap<(u32, u32), Note>,
+    images: Vec<((u32, u32), Image)>,
+    charts: Vec<((u32, u32), Chart)>,
+    open_row: Option<OpenRow>,
 }
 
+struct AutoFilter {
+    area: AutoFilterArea,
+    columns: BTreeMap<u32, FilterCriteria>,
+}
+
+enum AutoFilterArea {
+    Fixed(CellRange),
+    // from the header row (0-based) through the last written row and column
+    FromHeader(u32),
+}
+
+struct PendingCell {
+    value: CellValue,
+    style: Option<usize>,
+    // only used when write_row leaves the cell blank
+    fallback: bool,
+}
+
+// row being filled by positioned writes, written out once the sheet moves past it
+struct OpenRow {
+    row: u32, // 1-based
+    last_col: Option<u32>,
+    cells: BTreeMap<u32, PendingCell>,
+}
+
+// detached sheets are filled on other threads
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<SheetWriter>();
+};
+
 impl SheetWriter {
-    pub(crate) fn new(name: &str, style_reg: Arc<Mutex<StyleRegistry>>) -> Result<Self> {
+    pub(crate) fn new(
+        name: &str,
+        style_reg: Arc<Mutex<StyleRegistry>>,
+        shared_strings: Option<Arc<Mutex<SharedStringTable>>>,
+        table_reg: Arc<Mutex<TableRegistry>>,
+        date_1904: bool,
+        non_finite: NonFinitePolicy,
+    ) -> Result<Self> {
         let temp_file = NamedTempFile::new()?;
-        let mut writer = BufWriter::new(temp_file);
-
-        write!(
-            writer,
-            concat!(
-                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
-                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
-                r#"<sheetViews><sheetView workbookViewId="0"/></sheetViews>"#,
-                r#"<sheetFormatPr defaultRowHeight="15"/>"#,
-                r#"<sheetData>"#,
-            )
-        )?;
+        let writer = BufWriter::new(temp_file);
 
         Ok(SheetWriter {
             name: name.to_string(),
             temp: writer,
             current_row: 0,
             max_col: 0,
-            style_reg
+            style_reg,
+            shared_strings,
+            columns: BTreeMap::new(),
+            row_options: BTreeMap::new(),
+            row_pixels: BTreeMap::new(),
+            outline_level_row: 0,
+            outline_summary: None,
+            auto_fit: false,
+            col_chars: Vec::new(),
+            view: SheetView::default(),
+            pending: BTreeMap::new(),
+            merges: Vec::new(),
+            autofilter: None,
+            hyperlinks: Vec::new(),
+            hyperlink_style: None,
+            date_styles: [None; 4],
+            date_1904,
+            non_finite,
+            dynamic_arrays: false,
+            #[cfg(feature = "serde")]
+            record_header: None,
+            validations: Vec::new(),
+            conditional_formats: Vec::new(),
+            table_reg,
+            tables: Vec::new(),
+            print_area: None,
+            print_title_rows: None,
+            print_title_cols: None,
+            notes: BTreeMap::new(),
+            images: Vec::new(),
+            charts: Vec::new(),
+            open_row: None,
         })
     }
 
@@ -45,72 +169,1414 @@ impl SheetWriter {
         self.name.clone()
     }
 
+    // whether the sheet was created by the workbook owning `style_reg`
+    pub(crate) fn shares_styles(&self, style_reg: &Arc<Mutex<StyleRegistry>>) -> bool {
+        Arc::ptr_eq(&self.style_reg, style_reg)
+    }
+
+    /// Set width, hidden and outline state of a 0-based column.
+    pub fn set_column(&mut self, col: u32, options: ColumnOptions) -> Result<()> {
+        range::check_cell(0, col)?;
+        self.columns.insert(col, options);
+        Ok(())
+    }
+
+    pub fn set_column_width(&mut self, col: u32, width: f64) -> Result<()> {
+        let options = self.columns.get(&col).cloned().unwrap_or_default();
+        self.set_column(col, options.width(width))
+    }
+
+    /// Set height, hidden, outline state and style of a 0-based row. The row must not
+    /// have been written yet, though the row still being filled by `write_cell_at` is
+    /// fine; it is written even if it gets no cells.
+    pub fn set_row(&mut self, row: u32, options: RowOptions) -> Result<()> {
+        range::check_cell(row, 0)?;
+        let open = self
+            .open_row
+            .as_ref()
+            .is_some_and(|open| open.row == row + 1);
+        if row < self.current_row && !open {
+            return Err(MrXlsxError::OutOfOrder(format!(
+                "row {} of sheet '{}' has already been written",
+                row + 1,
+                self.name
+            )));
+        }
+        let style_idx = options
+            .style
+            .as_ref()
+            .map(|style| self.style_reg.lock().unwrap().register(style));
+        match options.pixels() {
+            Some(pixels) => self.row_pixels.insert(row, pixels),
+            None => self.row_pixels.remove(&row),
+        };
+        self.outline_level_row = self.outline_level_row.max(options.outline_level);
+        self.row_options.insert(row + 1, (options, style_idx));
+        Ok(())
+    }
+
+    pub fn set_row_height(&mut self, row: u32, height: f64) -> Result<()> {
+        range::check_cell(row, 0)?;
+        let options = match self.row_options.get(&(row + 1)) {
+            Some((options, _)) => options.clone(),
+            None => RowOptions::default(),
+        };
+        self.set_row(row, options.height(height))
+    }
+
+    /// Where outline groups keep their summary row and column. Excel's default is
+    /// below the detail rows and right of the detail columns.
+    pub fn set_outline_summary(&mut self, below: bool, right: bool) {
+        self.outline_summary = Some((below, right));
+    }
+
+    /// Track the longest value written to each column and size the columns to fit
+    /// when the sheet is finished. Explicit widths from `set_column` take precedence.
+    /// Cells in ranges merged before their row is written are not counted.
+    pub fn set_auto_fit(&mut self, enabled: bool) {
+        self.auto_fit = enabled;
+    }
+
+    /// Keep the top `rows` rows and left `cols` columns visible while scrolling.
+    pub fn freeze_panes(&mut self, rows: u32, cols: u32) {
+        self.view.pane = (rows > 0 || cols > 0).then_some(Pane::Frozen { rows, cols });
+    }
+
+    /// Split the window into scrollable panes, positions in points from the top-left corner.
+    pub fn split_panes(&mut self, x: f64, y: f64) {
+        self.view.pane = (x > 0.0 || y > 0.0).then_some(Pane::Split {
+            x: (x * 20.0).max(0.0),
+            y: (y * 20.0).max(0.0),
+        });
+    }
+
+    /// Zoom level in percent, clamped to Excel's 10-400 range.
+    pub fn set_zoom(&mut self, zoom: u16) {
+        self.view.zoom = Some(zoom.clamp(10, 400));
+    }
+
+    pub fn hide_gridlines(&mut self) {
+        self.view.hide_gridlines = true;
+    }
+
+    pub fn hide_headings(&mut self) {
+        self.view.hide_headings = true;
+    }
+
+    pub fn set_right_to_left(&mut self) {
+        self.view.right_to_left = true;
+    }
+
+    /// Set the active cell, 0-based.
+    pub fn set_selection(&mut self, row: u32, col: u32) {
+        self.view.selection = Some((row, col));
+    }
 
     pub fn write_row(&mut self, cells: &[CellValue]) -> Result<()> {
+        self.check_next_row(cells.len())?;
+        let replaced = cells
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| self.check_value(self.current_row, col as u32, cell))
+            .collect::<Result<Vec<_>>>()?;
+        self.close_open_row()?;
         self.current_row += 1;
         let row = self.current_row;
+        self.flush_pending_before(row)?;
+        let mut pending = self.pending.remove(&row).unwrap_or_default();
 
-        if cells.is_empty() {
+        if cells.is_empty() && pending.is_empty() && !self.row_options.contains_key(&row) {
             return Ok(());
         }
+        if cells.iter().any(|c| matches!(c, CellValue::Hyperlink(_))) {
+            self.ensure_hyperlink_style();
+        }
+        for cell in cells {
+            self.ensure_date_style(cell);
+        }
 
-        if cells.len() as u32 > self.max_col {
-            self.max_col = cells.len() as u32;
+        let mut rw = self.row_writer(row);
+        rw.open()?;
+        for (col_idx, (cell, replaced)) in cells.iter().zip(&replaced).enumerate() {
+            let col = col_idx as u32; // 0-based
+            let cell = replaced.as_ref().unwrap_or(cell);
+            match pending.remove(&col) {
+                Some(p) if !p.fallback || matches!(cell, CellValue::Blank) => {
+                    rw.cell(col, &p.value, p.style)?
+                }
+                _ => rw.cell(col, cell, None)?,
+            }
         }
+        for (col, p) in &pending {
+            rw.cell(*col, &p.value, p.style)?;
+        }
+        rw.close()
+    }
 
-        write!(self.temp, "<row r=\"{row}\">")?;
+    pub fn write_row_with_style(&mut self, cells: &[(CellValue, &Style)]) -> Result<()> {
+        self.check_next_row(cells.len())?;
+        let replaced = cells
+            .iter()
+            .enumerate()
+            .map(|(col, (cell, _))| self.check_value(self.current_row, col as u32, cell))
+            .collect::<Result<Vec<_>>>()?;
+        self.close_open_row()?;
+        self.current_row += 1;
+        let row = self.current_row;
+        self.flush_pending_before(row)?;
+        let mut pending = self.pending.remove(&row).unwrap_or_default();
+
+        if cells.is_empty() && pending.is_empty() && !self.row_options.contains_key(&row) {
+            return Ok(());
+        }
 
-        for (col_idx, cell) in cells.iter().enumerate() {
+        let style_reg = Arc::clone(&self.style_reg);
+        let mut reg = style_reg.lock().unwrap();
+        let mut rw = self.row_writer(row);
+        rw.open()?;
+        for (col_idx, ((cell, style), replaced)) in cells.iter().zip(&replaced).enumerate() {
             let col = col_idx as u32; // 0-based
-            let cell_ref = make_cell_ref(row, col); // e.g "A1", "B2"
-            write_cell(&mut self.temp, &cell_ref, cell, None)?;
+            let cell = replaced.as_ref().unwrap_or(cell);
+            match pending.remove(&col) {
+                Some(p) if !p.fallback || matches!(cell, CellValue::Blank) => {
+                    rw.cell(col, &p.value, p.style)?
+                }
+                _ => rw.cell(col, cell, Some(register_cell_style(&mut reg, cell, style)))?,
+            }
+        }
+        for (col, p) in &pending {
+            rw.cell(*col, &p.value, p.style)?;
+        }
+        rw.close()
+    }
+
+    /// Write a single cell at a 0-based position. Cells must be written in order: rows
+    /// top to bottom and, within a row, columns left to right. Skipped rows and columns
+    /// stay empty. `write_row` continues on the row after the last one written.
+    pub fn write_cell_at(&mut self, row: u32, col: u32, value: CellValue) -> Result<()> {
+        if matches!(value, CellValue::Hyperlink(_)) {
+            self.ensure_hyperlink_style();
         }
+        self.ensure_date_style(&value);
+        self.put_cell(row, col, value, None)
+    }
+
+    pub fn write_cell_at_with_style(
+        &mut self,
+        row: u32,
+        col: u32,
+        value: CellValue,
+        style: &Style,
+    ) -> Result<()> {
+        let style_idx = register_cell_style(&mut self.style_reg.lock().unwrap(), &value, style);
+        self.put_cell(row, col, value, Some(style_idx))
+    }
 
-        write!(self.temp, "</row>")?;
+    /// Write consecutive cells starting at a 0-based position, in the same order as
+    /// `write_cell_at`. Later cells of the row can still be added to the right.
+    pub fn write_row_at(&mut self, row: u32, first_col: u32, cells: &[CellValue]) -> Result<()> {
+        for (i, cell) in cells.iter().enumerate() {
+            self.write_cell_at(row, first_col + i as u32, cell.clone())?;
+        }
+        Ok(())
+    }
 
+    pub fn write_row_at_with_style(
+        &mut self,
+        row: u32,
+        first_col: u32,
+        cells: &[(CellValue, &Style)],
+    ) -> Result<()> {
+        for (i, (cell, style)) in cells.iter().enumerate() {
+            self.write_cell_at_with_style(row, first_col + i as u32, cell.clone(), style)?;
+        }
         Ok(())
     }
 
+    /// Write `record` as the next row. The first record also writes a header row of
+    /// its field names, and later records fill the columns by name. Nested structs
+    /// are flattened into `parent.field` columns and `None` is left blank. A nested
+    /// struct that is `None` in the first record gets a single column, so write such
+    /// records with `write_records`, which takes the columns from every record.
+    #[cfg(feature = "serde")]
+    pub fn serialize<T: serde::Serialize + ?Sized>(&mut self, record: &T) -> Result<()> {
+        self.write_record_cells(vec![record::record_cells(record)?])
+    }
 
-    pub fn write_row_with_style(&mut self, cells: &[(CellValue, &Style)]) -> Result<()> {
-        self.current_row += 1;
-        let row = self.current_row;
+    /// Write `records` as rows like `serialize`, with the header row covering the
+    /// fields of all of them.
+    #[cfg(feature = "serde")]
+    pub fn write_records<T: serde::Serialize>(&mut self, records: &[T]) -> Result<()> {
+        let records = records
+            .iter()
+            .map(record::record_cells)
+            .collect::<Result<Vec<_>>>()?;
+        self.write_record_cells(records)
+    }
+
+    #[cfg(feature = "serde")]
+    fn write_record_cells(&mut self, records: Vec<Vec<record::RecordCell>>) -> Result<()> {
+        let header = match self.record_header.take() {
+            Some(header) => header,
+            None if records.is_empty() => return Ok(()),
+            None => {
+                let header = record::record_header(&records);
+                let names: Vec<CellValue> = header.iter().map(CellValue::text).collect();
+                self.write_row(&names)?;
+                header
+            }
+        };
+        // map every record before writing, so a bad one leaves no partial rows
+        let rows = records
+            .into_iter()
+            .map(|record| record::record_row(&header, record))
+            .collect::<Result<Vec<_>>>();
+        self.record_header = Some(header);
+        rows?.iter().try_for_each(|row| self.write_row(row))
+    }
 
-        if cells.is_empty() {
+    // the next appended row, with `len` cells, must fit on the sheet
+    fn check_next_row(&self, len: usize) -> Result<()> {
+        let last_col = u32::try_from(len.saturating_sub(1)).unwrap_or(u32::MAX);
+        range::check_cell(self.current_row, last_col)
+    }
+
+    fn put_cell(
+        &mut self,
+        row: u32,
+        col: u32,
+        value: CellValue,
+        style: Option<usize>,
+    ) -> Result<()> {
+        range::check_cell(row, col)?;
+        let value = self.check_value(row, col, &value)?.unwrap_or(value);
+        let row = row + 1;
+        match &self.open_row {
+            Some(open) if open.row == row => {
+                if open.last_col.is_some_and(|last| col <= last) {
+                    return Err(MrXlsxError::OutOfOrder(format!(
+                        "cell {} of sheet '{}' is not right of the last cell written in its row",
+                        make_cell_ref(row, col),
+                        self.name
+                    )));
+                }
+            }
+            _ => {
+                if row <= self.current_row {
+                    return Err(MrXlsxError::OutOfOrder(format!(
+                        "row {row} of sheet '{}' has already been written",
+                        self.name
+                    )));
+                }
+                self.close_open_row()?;
+                self.flush_pending_before(row)?;
+                self.current_row = row;
+                self.open_row = Some(OpenRow {
+                    row,
+                    last_col: None,
+                    cells: self.pending.remove(&row).unwrap_or_default(),
+                });
+            }
+        }
+
+        let open = self.open_row.as_mut().unwrap();
+        open.last_col = Some(col);
+        match open.cells.get(&col) {
+            // merged and table cells win the same way as in write_row
+            Some(p) if !p.fallback || matches!(value, CellValue::Blank) => {}
+            _ => {
+                let cell = PendingCell {
+                    value,
+                    style,
+                    fallback: false,
+                };
+                open.cells.insert(col, cell);
+            }
+        }
+        Ok(())
+    }
+
+    fn close_open_row(&mut self) -> Result<()> {
+        let Some(open) = self.open_row.take() else {
+            return Ok(());
+        };
+        if open.cells.is_empty() {
             return Ok(());
         }
+        let mut rw = self.row_writer(open.row);
+        rw.open()?;
+        for (col, p) in &open.cells {
+            rw.cell(*col, &p.value, p.style)?;
+        }
+        rw.close()
+    }
 
-        if cells.len() as u32 > self.max_col {
-            self.max_col = cells.len() as u32;
+    /// Merge a block of cells, 0-based and inclusive. A non-blank `value` is placed in
+    /// the top-left cell and `style` is applied to every cell of the block; in that case
+    /// the merge owns the block and replaces anything written there by `write_row`, so
+    /// its first row must not have been written yet.
+    pub fn merge_range(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+        value: CellValue,
+        style: Option<&Style>,
+    ) -> Result<()> {
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        let value = self
+            .check_value(first_row, first_col, &value)?
+            .unwrap_or(value);
+        if range.is_single_cell() {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "{} is a single cell and cannot be merged",
+                range.to_ref()
+            )));
+        }
+        if let Some(existing) = self.merges.iter().find(|m| m.overlaps(&range)) {
+            return Err(MrXlsxError::OverlappingMerge(format!(
+                "{} overlaps {} on sheet '{}'",
+                range.to_ref(),
+                existing.to_ref(),
+                self.name
+            )));
         }
 
-        
-        write!(self.temp, "<row r=\"{row}\">")?;
+        if !matches!(value, CellValue::Blank) || style.is_some() {
+            if first_row < self.current_row {
+                return Err(MrXlsxError::OutOfOrder(format!(
+                    "row {} of sheet '{}' has already been written",
+                    first_row + 1,
+                    self.name
+                )));
+            }
+            let style_idx = match (&value, style) {
+                (value, Some(s)) => Some(register_cell_style(
+                    &mut self.style_reg.lock().unwrap(),
+                    value,
+                    s,
+                )),
+                (CellValue::Hyperlink(_), None) => Some(self.ensure_hyperlink_style()),
+                (value, None) => self.ensure_date_style(value),
+            };
+            let mut value = value;
+            for row in first_row..=last_row {
+                let cells = self.pending.entry(row + 1).or_default();
+                for col in first_col..=last_col {
+                    cells.insert(
+                        col,
+                        PendingCell {
+                            value: std::mem::replace(&mut value, CellValue::Blank),
+                            style: style_idx,
+                            fallback: false,
+                        },
+                    );
+                }
+            }
+        }
 
-        let mut reg = self.style_reg.lock().unwrap();
-        for (col_idx, (cell, style)) in cells.iter().enumerate() {
-            let style_idx = reg.register(style);
-            let col = col_idx as u32; // 0-based
-            let cell_ref = make_cell_ref(row, col); // e.g "A1", "B2"
-            write_cell(&mut self.temp, &cell_ref, cell, Some(style_idx))?; 
+        self.merges.push(range);
+        Ok(())
+    }
+
+    /// Add filter dropdowns to a header row over a block of cells, 0-based and inclusive.
+    pub fn autofilter(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+    ) -> Result<()> {
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        self.autofilter = Some(AutoFilter {
+            area: AutoFilterArea::Fixed(range),
+            columns: BTreeMap::new(),
+        });
+        Ok(())
+    }
+
+    /// Add filter dropdowns to `header_row` (0-based), covering every column and every
+    /// row written below it by the time the workbook is finished.
+    pub fn autofilter_from_header(&mut self, header_row: u32) {
+        self.autofilter = Some(AutoFilter {
+            area: AutoFilterArea::FromHeader(header_row),
+            columns: BTreeMap::new(),
+        });
+    }
+
+    /// Pre-set filter criteria on a 0-based sheet column inside the autofilter.
+    pub fn filter_column(&mut self, col: u32, criteria: FilterCriteria) -> Result<()> {
+        let Some(filter) = self.autofilter.as_mut() else {
+            return Err(MrXlsxError::NotFound(format!(
+                "autofilter on sheet '{}'",
+                self.name
+            )));
+        };
+        if let AutoFilterArea::Fixed(range) = filter.area
+            && !(range.first_col..=range.last_col).contains(&col)
+        {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "column {col} is outside autofilter {}",
+                range.to_ref()
+            )));
         }
+        filter.columns.insert(col, criteria);
+        Ok(())
+    }
+
+    /// Validate input on a block of cells, 0-based and inclusive.
+    pub fn add_data_validation(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+        validation: DataValidation,
+    ) -> Result<()> {
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        validation.validate()?;
+        self.validations.push((range, validation));
+        Ok(())
+    }
+
+    /// Format a block of cells, 0-based and inclusive, when a rule matches. Rules are
+    /// evaluated in the order they are added.
+    pub fn add_conditional_format(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+        format: ConditionalFormat,
+    ) -> Result<()> {
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        let dxf_id = format
+            .style
+            .as_ref()
+            .map(|s| self.style_reg.lock().unwrap().register_dxf(s));
+        self.conditional_formats.push((range, format, dxf_id));
+        Ok(())
+    }
+
+    /// Declare a block of cells, 0-based and inclusive, as an Excel table. The table
+    /// name must be unique across the workbook.
+    pub fn add_table(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+        table: Table,
+    ) -> Result<()> {
+        let range = CellRange::new(first_row, first_col, last_row, last_col)?;
+        if let Some(existing) = self.tables.iter().find(|t| t.range.overlaps(&range)) {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "{} overlaps table '{}' on sheet '{}'",
+                range.to_ref(),
+                existing.name,
+                self.name
+            )));
+        }
+        if let Some(merge) = self.merges.iter().find(|m| m.overlaps(&range)) {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "{} overlaps merged cells {} on sheet '{}'",
+                range.to_ref(),
+                merge.to_ref(),
+                self.name
+            )));
+        }
+
+        let mut reg = self.table_reg.lock().unwrap();
+        let name = reg.reserve(table.name.as_deref())?;
+        let table = match SheetTable::new(name.clone(), range, table) {
+            Ok(t) => t,
+            Err(e) => {
+                reg.release(&name);
+                return Err(e);
+            }
+        };
         drop(reg);
 
-        write!(self.temp, "</row>")?;
+        self.queue_table_cells(&table);
+        self.tables.push(table);
+        Ok(())
+    }
+
+    // fill header, calculated column and totals cells of rows not written yet
+    fn queue_table_cells(&mut self, table: &SheetTable) {
+        let range = table.range;
+        let mut queue = |row: u32, col: u32, value: CellValue| {
+            if row >= self.current_row {
+                self.pending
+                    .entry(row + 1)
+                    .or_default()
+                    .entry(col)
+                    .or_insert(PendingCell {
+                        value,
+                        style: None,
+                        fallback: true,
+                    });
+            }
+        };
+
+        for (i, column) in table.table.columns.iter().enumerate() {
+            let col = range.first_col + i as u32;
+            if table.table.header_row {
+                queue(range.first_row, col, CellValue::text(&column.name));
+            }
+            if let Some(formula) = table.calculated_formula(column) {
+                for row in table.data_rows() {
+                    queue(row, col, CellValue::formula(&formula));
+                }
+            }
+            if table.table.totals_row {
+                if let Some(formula) = table.total_formula(column) {
+                    queue(range.last_row, col, CellValue::formula(formula));
+                } else if let Some(label) = &column.total_label {
+                    queue(range.last_row, col, CellValue::text(label));
+                }
+            }
+        }
+    }
+
+    /// Attach a note to a 0-based cell, replacing any note already there. The cell
+    /// does not need to be written.
+    pub fn add_note(&mut self, row: u32, col: u32, note: Note) -> Result<()> {
+        range::check_cell(row, col)?;
+        self.notes.insert((row, col), note);
+        Ok(())
+    }
+
+    pub(crate) fn notes(&self) -> &BTreeMap<(u32, u32), Note> {
+        &self.notes
+    }
+
+    /// Place an image with its top-left corner in a 0-based cell.
+    pub fn insert_image(&mut self, row: u32, col: u32, image: Image) -> Result<()> {
+        range::check_cell(row, col)?;
+        self.images.push(((row, col), image));
+        Ok(())
+    }
+
+    pub(crate) fn images(&self) -> impl Iterator<Item = &Image> {
+        self.images.iter().map(|(_, image)| image)
+    }
+
+    /// Place a chart with its top-left corner in a 0-based cell.
+    pub fn insert_chart(&mut self, row: u32, col: u32, chart: Chart) -> Result<()> {
+        range::check_cell(row, col)?;
+        chart.validate()?;
+        self.charts.push(((row, col), chart));
+        Ok(())
+    }
+
+    pub(crate) fn charts(&self) -> impl Iterator<Item = &Chart> {
+        self.charts.iter().map(|(_, chart)| chart)
+    }
+
+    /// `xl/drawings/drawingN.xml` and its relationships, `media` holding the target
+    /// of each image in insertion order and charts numbered from `first_chart`.
+    pub(crate) fn drawing_xml(
+        &self,
+        media: &[String],
+        first_chart: usize,
+    ) -> (String, Relationships) {
+        let mut rels = Relationships::default();
+        let mut anchors = vec![];
+        for (i, ((row, col), image)) in self.images.iter().enumerate() {
+            let r_id = rels.add(REL_IMAGE, &media[i], false);
+            let anchor = Anchor::new(
+                (*row, *col),
+                (image.x_offset, image.y_offset),
+                image.display_size(),
+                |c| self.column_pixels(c),
+                |r| self.row_pixels(r),
+            );
+            let picture = picture_xml(i + 1, image.alt_text.as_deref(), &r_id, &anchor);
+            anchors.push(anchor.to_xml(image.anchor, &picture));
+        }
+        for (i, ((row, col), chart)) in self.charts.iter().enumerate() {
+            let target = format!("../charts/chart{}.xml", first_chart + i);
+            let r_id = rels.add(REL_CHART, &target, false);
+            let anchor = Anchor::new(
+                (*row, *col),
+                (chart.x_offset, chart.y_offset),
+                (chart.width, chart.height),
+                |c| self.column_pixels(c),
+                |r| self.row_pixels(r),
+            );
+            let frame = chart_frame_xml(anchors.len() + 1, &r_id, Some(&anchor));
+            anchors.push(anchor.to_xml(chart.anchor, &frame));
+        }
+        (drawing_xml(&anchors), rels)
+    }
+
+    /// `xl/drawings/vmlDrawingN.vml` with the note boxes placed over this sheet's
+    /// columns and rows.
+    pub(crate) fn vml_xml(&self, first_block: usize) -> String {
+        note::vml_xml(
+            &self.notes,
+            first_block,
+            |c| self.column_pixels(c),
+            |r| self.row_pixels(r),
+        )
+    }
 
+    fn row_pixels(&self, row: u32) -> u32 {
+        self.row_pixels
+            .get(&row)
+            .copied()
+            .unwrap_or(DEFAULT_ROW_PIXELS)
+    }
+
+    fn column_pixels(&self, col: u32) -> u32 {
+        let options = self.columns.get(&col);
+        if options.is_some_and(|o| o.hidden) {
+            return 0;
+        }
+        let auto_width = self
+            .col_chars
+            .get(col as usize)
+            .filter(|&&chars| self.auto_fit && chars > 0)
+            .map(|&chars| auto_fit_width(chars));
+        let width = options.and_then(|o| o.custom_width()).or(auto_width);
+        width_to_pixels(width.unwrap_or(DEFAULT_COLUMN_WIDTH))
+    }
+
+    /// Limit printing to a block of cells, 0-based and inclusive.
+    pub fn set_print_area(
+        &mut self,
+        first_row: u32,
+        first_col: u32,
+        last_row: u32,
+        last_col: u32,
+    ) -> Result<()> {
+        self.print_area = Some(CellRange::new(first_row, first_col, last_row, last_col)?);
+        Ok(())
+    }
+
+    /// Repeat rows `first_row..=last_row` (0-based) at the top of every printed page.
+    pub fn set_print_title_rows(&mut self, first_row: u32, last_row: u32) -> Result<()> {
+        CellRange::new(first_row, 0, last_row, 0)?;
+        self.print_title_rows = Some((first_row, last_row));
+        Ok(())
+    }
+
+    /// Repeat columns `first_col..=last_col` (0-based) on the left of every printed page.
+    pub fn set_print_title_cols(&mut self, first_col: u32, last_col: u32) -> Result<()> {
+        CellRange::new(0, first_col, 0, last_col)?;
+        self.print_title_cols = Some((first_col, last_col));
+        Ok(())
+    }
+
+    pub(crate) fn print_area(&self) -> Option<CellRange> {
+        self.print_area
+    }
+
+    pub(crate) fn print_title_rows(&self) -> Option<(u32, u32)> {
+        self.print_title_rows
+    }
+
+    pub(crate) fn print_title_cols(&self) -> Option<(u32, u32)> {
+        self.print_title_cols
+    }
+
+    pub(crate) fn tables(&self) -> &[SheetTable] {
+        &self.tables
+    }
+
+    pub(crate) fn has_dynamic_arrays(&self) -> bool {
+        self.dynamic_arrays
+    }
+
+    pub(crate) fn autofilter_range(&self) -> Option<CellRange> {
+        let filter = self.autofilter.as_ref()?;
+        match filter.area {
+            AutoFilterArea::Fixed(range) => Some(range),
+            AutoFilterArea::FromHeader(row) => Some(CellRange {
+                first_row: row,
+                first_col: 0,
+                last_row: row.max(self.current_row.saturating_sub(1)),
+                last_col: self.max_col.saturating_sub(1),
+            }),
+        }
+    }
+
+    fn ensure_hyperlink_style(&mut self) -> usize {
+        *self
+            .hyperlink_style
+            .get_or_insert_with(|| self.style_reg.lock().unwrap().hyperlink_style())
+    }
+
+    // reject dates outside the workbook's date system, array formulas running off
+    // the sheet from the 0-based cell and non-finite numbers the policy refuses
+    // before any of the row is written; returns the policy's replacement for a
+    // non-finite number
+    fn check_value(&self, row: u32, col: u32, value: &CellValue) -> Result<Option<CellValue>> {
+        match value {
+            CellValue::Date(date) => date.serial(self.date_1904).map(|_| None),
+            CellValue::DateTime(datetime) => datetime.serial(self.date_1904).map(|_| None),
+            CellValue::Formula(formula) => formula.check_extent(row, col).map(|_| None),
+            _ => self.non_finite.replace(value),
+        }
+    }
+
+    // register the default format of a date or time value on first use
+    fn ensure_date_style(&mut self, value: &CellValue) -> Option<usize> {
+        let slot = date_slot(value)?;
+        let style_reg = &self.style_reg;
+        let idx = *self.date_styles[slot].get_or_insert_with(|| {
+            let style = Style::new().format(DATE_FORMATS[slot].clone());
+            style_reg.lock().unwrap().register(&style)
+        });
+        Some(idx)
+    }
+
+    fn row_writer(&mut self, row: u32) -> RowWriter<'_> {
+        let attrs = match self.row_options.remove(&row) {
+            Some((options, style_idx)) => options.to_attrs(style_idx),
+            None => String::new(),
+        };
+        // a merged value spans its columns, so it does not size any of them
+        let merged_cols = if self.auto_fit {
+            self.merges
+                .iter()
+                .filter(|m| m.first_row < row && row <= m.last_row + 1)
+                .map(|m| (m.first_col, m.last_col))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        RowWriter {
+            w: &mut self.temp,
+            hyperlinks: &mut self.hyperlinks,
+            hyperlink_style: self.hyperlink_style,
+            date_styles: self.date_styles,
+            date_1904: self.date_1904,
+            dynamic_arrays: &mut self.dynamic_arrays,
+            sst: self.shared_strings.as_ref().map(|s| s.lock().unwrap()),
+            col_chars: self.auto_fit.then_some(&mut self.col_chars),
+            merged_cols,
+            max_col: &mut self.max_col,
+            row,
+            attrs,
+        }
+    }
+
+    fn flush_pending_before(&mut self, row: u32) -> Result<()> {
+        loop {
+            // rows with queued cells or options only
+            let next_pending = self.pending.keys().next().copied();
+            let next_options = self.row_options.keys().next().copied();
+            let Some(r) = next_pending.into_iter().chain(next_options).min() else {
+                break;
+            };
+            if r >= row {
+                break;
+            }
+            let cells = self.pending.remove(&r).unwrap_or_default();
+            self.current_row = self.current_row.max(r);
+            let mut rw = self.row_writer(r);
+            rw.open()?;
+            for (col, p) in &cells {
+                rw.cell(*col, &p.value, p.style)?;
+            }
+            rw.close()?;
+        }
         Ok(())
     }
 
     pub(crate) fn finalize(&mut self) -> Result<()> {
+        self.close_open_row()?;
+        self.flush_pending_before(u32::MAX)?;
+        self.temp.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn write_xml<W: Write>(
+        &mut self,
+        w: &mut W,
+        tab_selected: bool,
+        ids: &PartIds,
+    ) -> Result<Relationships> {
+        let mut rels = Relationships::default();
+
         write!(
-            self.temp,
+            w,
             concat!(
-                r#"</sheetData>"#,
-                r#"<pageMargins left="0.75" right="0.75" top="1" bottom="1" header="0.5" footer="0.5"/>"#,
-                r#"</worksheet>"#,
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+                r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
             )
         )?;
-        self.temp.flush()?;
+        if let Some((below, right)) = self.outline_summary {
+            write!(
+                w,
+                r#"<sheetPr><outlinePr summaryBelow="{}" summaryRight="{}"/></sheetPr>"#,
+                below as u8, right as u8
+            )?;
+        }
+        w.write_all(self.view.to_xml(tab_selected).as_bytes())?;
+
+        let mut format_pr = String::from(r#"<sheetFormatPr defaultRowHeight="15""#);
+        if self.outline_level_row > 0 {
+            format_pr.push_str(&format!(r#" outlineLevelRow="{}""#, self.outline_level_row));
+        }
+        let outline_level_col = self.columns.values().map(|c| c.outline_level).max();
+        if let Some(level) = outline_level_col.filter(|level| *level > 0) {
+            format_pr.push_str(&format!(r#" outlineLevelCol="{level}""#));
+        }
+        format_pr.push_str("/>");
+        w.write_all(format_pr.as_bytes())?;
+
+        w.write_all(self.cols_xml().as_bytes())?;
+
+        write!(w, "<sheetData>")?;
+        let temp_file = self.temp.get_mut();
+        temp_file.seek(SeekFrom::Start(0))?;
+        std::io::copy(temp_file, w)?;
+        write!(w, "</sheetData>")?;
+
+        if let Some(range) = self.autofilter_range() {
+            let filter = self.autofilter.as_ref().unwrap();
+            let mut columns = String::new();
+            for (col, criteria) in &filter.columns {
+                if (range.first_col..=range.last_col).contains(col) {
+                    columns.push_str(&criteria.to_xml(col - range.first_col));
+                }
+            }
+            if columns.is_empty() {
+                write!(w, "<autoFilter ref=\"{}\"/>", range.to_ref())?;
+            } else {
+                write!(
+                    w,
+                    "<autoFilter ref=\"{}\">{columns}</autoFilter>",
+                    range.to_ref()
+                )?;
+            }
+        }
+
+        if !self.merges.is_empty() {
+            write!(w, "<mergeCells count=\"{}\">", self.merges.len())?;
+            for range in &self.merges {
+                write!(w, "<mergeCell ref=\"{}\"/>", range.to_ref())?;
+            }
+            write!(w, "</mergeCells>")?;
+        }
+
+        for (i, (range, format, dxf_id)) in self.conditional_formats.iter().enumerate() {
+            write!(
+                w,
+                "<conditionalFormatting sqref=\"{}\">{}</conditionalFormatting>",
+                range.to_ref(),
+                format.to_xml(i as u32 + 1, *dxf_id)
+            )?;
+        }
+
+        if !self.validations.is_empty() {
+            write!(w, "<dataValidations count=\"{}\">", self.validations.len())?;
+            for (range, validation) in &self.validations {
+                w.write_all(validation.to_xml(&range.to_ref()).as_bytes())?;
+            }
+            write!(w, "</dataValidations>")?;
+        }
+
+        if !self.hyperlinks.is_empty() {
+            write!(w, "<hyperlinks>")?;
+            for (cell_ref, link) in &self.hyperlinks {
+                write!(w, "<hyperlink ref=\"{cell_ref}\"")?;
+                if let Some(target) = link.external_target() {
+                    let r_id = rels.add(REL_HYPERLINK, &target, true);
+                    write!(w, " r:id=\"{r_id}\"")?;
+                }
+                if let Some(location) = link.location() {
+                    write!(w, " location=\"{}\"", xml_escape(location))?;
+                }
+                if let Some(text) = &link.text {
+                    write!(w, " display=\"{}\"", xml_escape(text))?;
+                }
+                if let Some(tooltip) = &link.tooltip {
+                    write!(w, " tooltip=\"{}\"", xml_escape(tooltip))?;
+                }
+                write!(w, "/>")?;
+            }
+            write!(w, "</hyperlinks>")?;
+        }
+
+        write!(
+            w,
+            r#"<pageMargins left="0.75" right="0.75" top="1" bottom="1" header="0.5" footer="0.5"/>"#
+        )?;
+
+        if let Some(n) = ids.drawing {
+            let r_id = rels.add(REL_DRAWING, &format!("../drawings/drawing{n}.xml"), false);
+            write!(w, "<drawing r:id=\"{r_id}\"/>")?;
+        }
+
+        if let Some(n) = ids.comments {
+            rels.add(REL_COMMENTS, &format!("../comments{n}.xml"), false);
+            let r_id = rels.add(
+                REL_VML_DRAWING,
+                &format!("../drawings/vmlDrawing{n}.vml"),
+                false,
+            );
+            write!(w, "<legacyDrawing r:id=\"{r_id}\"/>")?;
+        }
+
+        if !self.tables.is_empty() {
+            write!(w, "<tableParts count=\"{}\">", self.tables.len())?;
+            for i in 0..self.tables.len() {
+                let target = format!("../tables/table{}.xml", ids.first_table + i);
+                let r_id = rels.add(REL_TABLE, &target, false);
+                write!(w, "<tablePart r:id=\"{r_id}\"/>")?;
+            }
+            write!(w, "</tableParts>")?;
+        }
+
+        write!(w, "</worksheet>")?;
+        Ok(rels)
+    }
+
+    fn cols_xml(&self) -> String {
+        let auto_cols = if self.auto_fit {
+            self.col_chars.len() as u32
+        } else {
+            0
+        };
+        let explicit_cols = self.columns.keys().next_back().map_or(0, |c| c + 1);
+        let col_count = auto_cols.max(explicit_cols);
+        if col_count == 0 {
+            return String::new();
+        }
+
+        let default_options = ColumnOptions::default();
+        let mut xml = String::from("<cols>");
+        for col in 0..col_count {
+            let auto_width = self
+                .col_chars
+                .get(col as usize)
+                .filter(|&&chars| self.auto_fit && chars > 0)
+                .map(|&chars| auto_fit_width(chars));
+            let options = self.columns.get(&col);
+            if options.is_none() && auto_width.is_none() {
+                continue;
+            }
+            let options = options.unwrap_or(&default_options);
+            xml.push_str(&options.to_xml(col, auto_width));
+        }
+        xml.push_str("</cols>");
+        xml
+    }
+}
+
+// default row height of 15 points
+const DEFAULT_ROW_PIXELS: u32 = 20;
+
+const DATE_FORMATS: [NumberFormat; 4] = [
+    NumberFormat::Date,
+    NumberFormat::Time,
+    NumberFormat::DateTime,
+    NumberFormat::Duration,
+];
+
+// index into `DATE_FORMATS` of date and time values
+fn date_slot(value: &CellValue) -> Option<usize> {
+    match value {
+        CellValue::Date(_) => Some(0),
+        CellValue::Time(_) => Some(1),
+        CellValue::DateTime(_) => Some(2),
+        CellValue::Duration(_) => Some(3),
+        _ => None,
+    }
+}
+
+// date and time values keep their default format unless the style sets one
+fn register_cell_style(reg: &mut StyleRegistry, value: &CellValue, style: &Style) -> usize {
+    match date_slot(value) {
+        Some(slot) if style.number_format == NumberFormat::General => {
+            reg.register(&style.clone().format(DATE_FORMATS[slot].clone()))
+        }
+        _ => reg.register(style),
+    }
+}
+
+struct RowWriter<'a> {
+    w: &'a mut BufWriter<NamedTempFile>,
+    hyperlinks: &'a mut Vec<(String, Hyperlink)>,
+    hyperlink_style: Option<usize>,
+    date_styles: [Option<usize>; 4],
+    date_1904: bool,
+    dynamic_arrays: &'a mut bool,
+    sst: Option<MutexGuard<'a, SharedStringTable>>,
+    col_chars: Option<&'a mut Vec<usize>>,
+    merged_cols: Vec<(u32, u32)>,
+    max_col: &'a mut u32,
+    row: u32,
+    attrs: String,
+}
+
+impl RowWriter<'_> {
+    fn open(&mut self) -> Result<()> {
+        write!(self.w, "<row r=\"{}\"{}>", self.row, self.attrs)?;
         Ok(())
     }
+
+    fn cell(&mut self, col: u32, value: &CellValue, style: Option<usize>) -> Result<()> {
+        let style = match value {
+            CellValue::Hyperlink(_) => style.or(self.hyperlink_style),
+            _ => style.or_else(|| date_slot(value).and_then(|slot| self.date_styles[slot])),
+        };
+        write_cell(
+            self.w,
+            self.row,
+            col,
+            value,
+            style,
+            self.sst.as_deref_mut(),
+            self.date_1904,
+        )?;
+        match value {
+            CellValue::Hyperlink(link) => {
+                let cell_ref = make_cell_ref(self.row, col); // e.g "A1", "B2"
+                self.hyperlinks.push((cell_ref, link.clone()));
+            }
+            CellValue::Formula(formula) if formula.is_dynamic() => *self.dynamic_arrays = true,
+            _ => {}
+        }
+        if let Some(col_chars) = self.col_chars.as_deref_mut()
+            && !self
+                .merged_cols
+                .iter()
+                .any(|&(first, last)| (first..=last).contains(&col))
+        {
+            track_width(col_chars, col, value);
+        }
+        if col + 1 > *self.max_col {
+            *self.max_col = col + 1;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        write!(self.w, "</row>")?;
+        Ok(())
+    }
+}
+
+fn track_width(col_chars: &mut Vec<usize>, col: u32, cell: &CellValue) {
+    let chars = match cell {
+        CellValue::Text(text) => text.lines().map(|l| l.chars().count()).max().unwrap_or(0),
+        CellValue::RichText(runs) => {
+            let text: String = runs.iter().map(|run| run.text.as_str()).collect();
+            text.lines().map(|l| l.chars().count()).max().unwrap_or(0)
+        }
+        CellValue::Hyperlink(link) => link.display_text().chars().count(),
+        // General format shows at most 11 characters of a number
+        CellValue::Number(n) => n.to_string().len().min(11),
+        CellValue::Bool(b) => {
+            if *b {
+                4
+            } else {
+                5
+            }
+        }
+        // lengths of the default date and time formats
+        CellValue::Date(_) => 10,
+        CellValue::Time(_) | CellValue::Duration(_) => 8,
+        CellValue::DateTime(_) => 16,
+        CellValue::Formula(formula) => match &formula.result {
+            Some(FormulaResult::Text(text)) => text.chars().count(),
+            Some(FormulaResult::Number(n)) => n.to_string().len().min(11),
+            _ => 0,
+        },
+        CellValue::Error(e) => e.as_str().len(),
+        CellValue::Blank => 0,
+    };
+    let col = col as usize;
+    if col_chars.len() <= col {
+        col_chars.resize(col + 1, 0);
+    }
+    if chars > col_chars[col] {
+        col_chars[col] = chars;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SheetWriter;
+    use crate::{
+        error::MrXlsxError,
+        workbook::{
+            Workbook,
+            cell::{CellValue, NonFinitePolicy},
+            chart::{Chart, ChartSeries, ChartType},
+            column::ColumnOptions,
+            formula::Formula,
+            image::Image,
+            note::Note,
+            range::{MAX_COLS, MAX_ROWS},
+            row::RowOptions,
+            style::Style,
+            testing::{attr_map, attrs, write, write_with, written_part},
+        },
+    };
+
+    const SHEET: &str = "xl/worksheets/sheet1.xml";
+
+    // XML of a sheet filled by `build` in a default workbook
+    fn sheet_xml(build: impl FnOnce(&mut SheetWriter)) -> String {
+        written_part(
+            |wb: &mut Workbook| build(wb.add_sheet("Data").unwrap()),
+            SHEET,
+        )
+    }
+
+    #[test]
+    fn non_finite_numbers_are_replaced_in_every_write() {
+        let written = write_with(
+            |builder| builder.non_finite(NonFinitePolicy::Blank),
+            |wb| {
+                let sheet = wb.add_sheet("Data").unwrap();
+                sheet
+                    .write_row(&[CellValue::Number(f64::NAN), CellValue::Number(1.0)])
+                    .unwrap();
+                sheet
+                    .write_row_with_style(&[(
+                        CellValue::Number(f64::INFINITY),
+                        &Style::new().bold(),
+                    )])
+                    .unwrap();
+                sheet
+                    .write_cell_at(2, 0, CellValue::Number(f64::NAN))
+                    .unwrap();
+                sheet
+                    .merge_range(3, 0, 3, 1, CellValue::Number(f64::NAN), None)
+                    .unwrap();
+            },
+        );
+
+        let xml = written.part(SHEET);
+        assert!(!xml.contains("NaN") && !xml.contains("inf"), "{xml}");
+        assert!(xml.contains(r#"<c r="B1"><v>1</v></c>"#), "{xml}");
+    }
+
+    #[test]
+    fn auto_fit_skips_merged_cells() {
+        let xml = sheet_xml(|sheet| {
+            sheet.set_auto_fit(true);
+            let title = CellValue::text("A title much wider than its columns");
+            sheet.merge_range(0, 0, 0, 2, title, None).unwrap();
+            sheet.write_cell_at(1, 0, CellValue::text("x")).unwrap();
+            sheet
+                .write_cell_at(1, 1, CellValue::text("x".repeat(20)))
+                .unwrap();
+        });
+
+        assert_eq!(
+            attrs(&xml, "col"),
+            [
+                attr_map([
+                    ("min", "1"),
+                    ("max", "1"),
+                    ("width", "8.43"),
+                    ("customWidth", "1")
+                ]),
+                attr_map([
+                    ("min", "2"),
+                    ("max", "2"),
+                    ("width", "24"),
+                    ("customWidth", "1")
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn array_formulas_must_fit_the_sheet() {
+        let xml = sheet_xml(|sheet| {
+            let array = |rows, cols| CellValue::Formula(Formula::new("A1:B2*2").array(rows, cols));
+            assert!(matches!(
+                sheet.write_row(&[CellValue::Blank, array(1, MAX_COLS)]),
+                Err(MrXlsxError::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                sheet.write_cell_at(MAX_ROWS - 1, 0, array(2, 1)),
+                Err(MrXlsxError::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                sheet.merge_range(MAX_ROWS - 2, 0, MAX_ROWS - 1, 1, array(3, 1), None),
+                Err(MrXlsxError::InvalidArgument(_))
+            ));
+            sheet
+                .write_cell_at(MAX_ROWS - 2, MAX_COLS - 2, array(2, 2))
+                .unwrap();
+        });
+
+        assert_eq!(
+            attrs(&xml, "f"),
+            [attr_map([("t", "array"), ("ref", "XFC1048575:XFD1048576")])]
+        );
+    }
+
+    #[test]
+    fn merge_range_rejects_bad_ranges() {
+        let xml = sheet_xml(|sheet| {
+            let mut merge = |first_row, first_col, last_row, last_col| {
+                sheet.merge_range(
+                    first_row,
+                    first_col,
+                    last_row,
+                    last_col,
+                    CellValue::Blank,
+                    None,
+                )
+            };
+            merge(1, 1, 2, 3).unwrap();
+            for (first_row, first_col, last_row, last_col) in [
+                (1, 1, 2, 3),
+                (0, 0, 1, 1),
+                (2, 3, 4, 4),
+                (0, 0, 5, 5),
+                (2, 2, 2, 3),
+            ] {
+                assert!(matches!(
+                    merge(first_row, first_col, last_row, last_col),
+                    Err(MrXlsxError::OverlappingMerge(_))
+                ));
+            }
+            for (first_row, first_col, last_row, last_col) in [
+                (0, 0, MAX_ROWS, 0),
+                (0, 0, 0, MAX_COLS),
+                (5, 0, 4, 1),
+                (0, 5, 1, 4),
+                (7, 7, 7, 7),
+            ] {
+                assert!(matches!(
+                    merge(first_row, first_col, last_row, last_col),
+                    Err(MrXlsxError::InvalidRange(_))
+                ));
+            }
+            // touching ranges do not overlap
+            merge(3, 1, 3, 3).unwrap();
+            merge(1, 4, 2, 4).unwrap();
+        });
+
+        assert_eq!(attrs(&xml, "mergeCells")[0]["count"], "3");
+        let refs: Vec<_> = attrs(&xml, "mergeCell")
+            .into_iter()
+            .map(|merge| merge["ref"].clone())
+            .collect();
+        assert_eq!(refs, ["B2:D3", "B4:D4", "E2:E3"]);
+    }
+
+    #[test]
+    fn set_row_applies_to_the_open_row() {
+        let xml = sheet_xml(|sheet| {
+            sheet.write_cell_at(0, 0, CellValue::Number(1.0)).unwrap();
+            sheet
+                .set_row(0, RowOptions::default().height(30.0))
+                .unwrap();
+            sheet.write_cell_at(0, 1, CellValue::Number(2.0)).unwrap();
+            sheet.write_cell_at(1, 0, CellValue::Number(3.0)).unwrap();
+            assert!(matches!(
+                sheet.set_row(0, RowOptions::default()),
+                Err(MrXlsxError::OutOfOrder(_))
+            ));
+        });
+
+        assert_eq!(
+            attrs(&xml, "row"),
+            [
+                attr_map([("r", "1"), ("ht", "30"), ("customHeight", "1")]),
+                attr_map([("r", "2")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn cells_outside_the_sheet_are_rejected() {
+        write(|wb| {
+            let sheet = wb.add_sheet("Data").unwrap();
+            for (row, col) in [(u32::MAX, 0), (MAX_ROWS, 0), (0, MAX_COLS), (0, u32::MAX)] {
+                assert!(matches!(
+                    sheet.write_cell_at(row, col, CellValue::Number(1.0)),
+                    Err(MrXlsxError::InvalidRange(_))
+                ));
+            }
+            assert!(matches!(
+                sheet.set_row(u32::MAX, RowOptions::default()),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+            assert!(matches!(
+                sheet.set_row_height(MAX_ROWS, 20.0),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+            assert!(matches!(
+                sheet.set_column(MAX_COLS, ColumnOptions::new()),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+            assert!(matches!(
+                sheet.set_column_width(u32::MAX, 20.0),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+            sheet.set_column_width(MAX_COLS - 1, 20.0).unwrap();
+            assert!(matches!(
+                sheet.merge_range(0, 0, u32::MAX, 1, CellValue::Blank, None),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+
+            for (row, col) in [(MAX_ROWS, 0), (0, MAX_COLS)] {
+                assert!(matches!(
+                    sheet.add_note(row, col, Note::new("x")),
+                    Err(MrXlsxError::InvalidRange(_))
+                ));
+            }
+            sheet
+                .add_note(MAX_ROWS - 1, MAX_COLS - 1, Note::new("x"))
+                .unwrap();
+
+            // signature, IHDR length and type, then a 1x1 size
+            let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+            png.extend([0, 0, 0, 1, 0, 0, 0, 1]);
+            let image = Image::new(png).unwrap();
+            for (row, col) in [(MAX_ROWS, 0), (0, MAX_COLS)] {
+                assert!(matches!(
+                    sheet.insert_image(row, col, image.clone()),
+                    Err(MrXlsxError::InvalidRange(_))
+                ));
+            }
+            sheet
+                .insert_image(MAX_ROWS - 1, MAX_COLS - 1, image.scale(1e12, 1e12))
+                .unwrap();
+
+            let chart = Chart::new(ChartType::Line).add_series(ChartSeries::new("Data!$A$1:$A$2"));
+            for (row, col) in [(MAX_ROWS, 0), (0, MAX_COLS)] {
+                assert!(matches!(
+                    sheet.insert_chart(row, col, chart.clone()),
+                    Err(MrXlsxError::InvalidRange(_))
+                ));
+            }
+            sheet
+                .insert_chart(
+                    MAX_ROWS - 1,
+                    MAX_COLS - 1,
+                    chart.size(u32::MAX, u32::MAX).offset(u32::MAX, u32::MAX),
+                )
+                .unwrap();
+
+            let row = vec![CellValue::Number(1.0); MAX_COLS as usize + 1];
+            assert!(matches!(
+                sheet.write_row(&row),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+            sheet
+                .write_cell_at(MAX_ROWS - 1, MAX_COLS - 1, CellValue::Number(1.0))
+                .unwrap();
+            assert!(matches!(
+                sheet.write_row(&[CellValue::Number(1.0)]),
+                Err(MrXlsxError::InvalidRange(_))
+            ));
+        });
+    }
 }
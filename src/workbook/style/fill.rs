@@ -16,4 +16,15 @@ impl Fill {
             ),
         }
     }
+
+    // differential fills take the solid color from bgColor
+    pub(crate) fn to_dxf_xml(&self) -> String {
+        match self {
+            Fill::None => "<fill><patternFill/></fill>".into(),
+            Fill::Solid(c) => format!(
+                "<fill><patternFill patternType=\"solid\"><fgColor rgb=\"{0}\"/><bgColor rgb=\"{0}\"/></patternFill></fill>",
+                c.as_argb()
+            ),
+        }
+    }
 }
@@ -1,10 +1,30 @@
-use crate::workbook::style::color::Color;
+use crate::workbook::{style::color::Color, xml_escape};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VertAlign {
+    #[default]
+    Baseline,
+    Superscript,
+    Subscript,
+}
+
+impl VertAlign {
+    fn to_xml(self) -> &'static str {
+        match self {
+            VertAlign::Baseline => "",
+            VertAlign::Superscript => "<vertAlign val=\"superscript\"/>",
+            VertAlign::Subscript => "<vertAlign val=\"subscript\"/>",
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Font {
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    pub strike: bool,
+    pub vert_align: VertAlign,
     pub size: u32,
     pub color: Option<Color>,
     pub name: String,
@@ -16,6 +36,8 @@ impl Default for Font {
             bold: false,
             italic: false,
             underline: false,
+            strike: false,
+            vert_align: VertAlign::Baseline,
             size: 220,
             color: None,
             name: "Calibri".into(),
@@ -32,13 +54,17 @@ impl Font {
         if self.italic {
             xml.push_str("<i/>");
         }
+        if self.strike {
+            xml.push_str("<strike/>");
+        }
         if self.underline {
             xml.push_str("<u/>");
         }
+        xml.push_str(self.vert_align.to_xml());
 
         let pt = self.size / 20;
         xml.push_str(&format!("<sz val=\"{pt}\"/>"));
-        xml.push_str(&format!("<name val=\"{}\"/>", self.name));
+        xml.push_str(&format!("<name val=\"{}\"/>", xml_escape(&self.name)));
 
         if let Some(c) = &self.color {
             xml.push_str(&format!("<color rgb=\"{}\"/>", c.as_argb()));
@@ -47,4 +73,96 @@ impl Font {
         xml.push_str("</font>");
         xml
     }
+
+    // only the parts that differ from the default font, so the cell keeps the rest
+    pub(crate) fn to_dxf_xml(&self) -> String {
+        let default = Font::default();
+        let mut xml = String::from("<font>");
+        if self.bold {
+            xml.push_str("<b/>");
+        }
+        if self.italic {
+            xml.push_str("<i/>");
+        }
+        if self.strike {
+            xml.push_str("<strike/>");
+        }
+        if self.underline {
+            xml.push_str("<u/>");
+        }
+        xml.push_str(self.vert_align.to_xml());
+        if self.size != default.size {
+            xml.push_str(&format!("<sz val=\"{}\"/>", self.size / 20));
+        }
+        if let Some(c) = &self.color {
+            xml.push_str(&format!("<color rgb=\"{}\"/>", c.as_argb()));
+        }
+        if self.name != default.name {
+            xml.push_str(&format!("<name val=\"{}\"/>", xml_escape(&self.name)));
+        }
+        xml.push_str("</font>");
+        xml
+    }
+
+    // run properties of a rich text run
+    pub(crate) fn to_rpr_xml(&self) -> String {
+        let mut xml = String::from("<rPr>");
+        if self.bold {
+            xml.push_str("<b/>");
+        }
+        if self.italic {
+            xml.push_str("<i/>");
+        }
+        if self.strike {
+            xml.push_str("<strike/>");
+        }
+        if self.underline {
+            xml.push_str("<u/>");
+        }
+        xml.push_str(self.vert_align.to_xml());
+        xml.push_str(&format!("<sz val=\"{}\"/>", self.size / 20));
+        if let Some(c) = &self.color {
+            xml.push_str(&format!("<color rgb=\"{}\"/>", c.as_argb()));
+        }
+        xml.push_str(&format!("<rFont val=\"{}\"/>", xml_escape(&self.name)));
+        xml.push_str("</rPr>");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strike_and_vert_align_are_written() {
+        let font = Font {
+            strike: true,
+            vert_align: VertAlign::Superscript,
+            ..Font::default()
+        };
+        assert_eq!(
+            font.to_xml(),
+            "<font><strike/><vertAlign val=\"superscript\"/><sz val=\"11\"/><name val=\"Calibri\"/></font>"
+        );
+        assert_eq!(
+            font.to_dxf_xml(),
+            "<font><strike/><vertAlign val=\"superscript\"/></font>"
+        );
+        assert_eq!(
+            Font::default().to_xml(),
+            "<font><sz val=\"11\"/><name val=\"Calibri\"/></font>"
+        );
+    }
+
+    #[test]
+    fn name_is_escaped() {
+        let font = Font {
+            name: "A&B \"Sans\"".to_string(),
+            ..Font::default()
+        };
+        let name = "<name val=\"A&amp;B &quot;Sans&quot;\"/>";
+        assert!(font.to_xml().contains(name), "{}", font.to_xml());
+        assert_eq!(font.to_dxf_xml(), format!("<font>{name}</font>"));
+    }
 }
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 
-use crate::workbook::style::{
-    border::{Border, BorderStyle},
-    color::Color,
-    fill::Fill,
-    font::Font,
-    number::NumberFormat,
+use crate::workbook::{
+    style::{
+        border::{Border, BorderStyle},
+        color::Color,
+        fill::Fill,
+        font::{Font, VertAlign},
+        number::NumberFormat,
+    },
+    xml_escape,
 };
 pub mod border;
 pub mod color;
@@ -13,7 +16,7 @@ pub mod fill;
 pub mod font;
 pub mod number;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Style {
     pub font: Font,
     pub fill: Fill,
@@ -49,6 +52,18 @@ impl Style {
         self.font.underline = true;
         self
     }
+    pub fn strike(mut self) -> Self {
+        self.font.strike = true;
+        self
+    }
+    pub fn superscript(mut self) -> Self {
+        self.font.vert_align = VertAlign::Superscript;
+        self
+    }
+    pub fn subscript(mut self) -> Self {
+        self.font.vert_align = VertAlign::Subscript;
+        self
+    }
     pub fn font_size(mut self, pt: u32) -> Self {
         self.font.size = pt * 20;
         self
@@ -115,8 +130,13 @@ pub(crate) struct StyleRegistry {
     fill_index: HashMap<Fill, usize>,
     border_index: HashMap<Border, usize>,
     num_fmt_index: HashMap<String, u32>,
-    xfs: Vec<(usize, usize, usize, u32)>,
-    xf_index: HashMap<(usize, usize, usize, u32), usize>,
+    xfs: Vec<(usize, usize, usize, u32, usize)>,
+    xf_index: HashMap<(usize, usize, usize, u32, usize), usize>,
+    // fonts of the named cell styles after "Normal"
+    cell_style_xfs: Vec<usize>,
+    hyperlink_xf: Option<usize>,
+    dxfs: Vec<Style>,
+    dxf_index: HashMap<Style, usize>,
 
     next_num_fmt_id: u32,
 }
@@ -134,6 +154,10 @@ impl StyleRegistry {
             num_fmt_index:   HashMap::new(),
             xfs:             Vec::new(),
             xf_index:        HashMap::new(),
+            cell_style_xfs:  Vec::new(),
+            hyperlink_xf:    None,
+            dxfs:            Vec::new(),
+            dxf_index:       HashMap::new(),
             next_num_fmt_id: 164,
         };
 
@@ -147,7 +171,7 @@ impl StyleRegistry {
         reg.borders.push(Border::default());
         reg.border_index.insert(Border::default(), 0);
 
-        reg.intern_xf(0, 0, 0, 0);
+        reg.intern_xf(0, 0, 0, 0, 0);
 
         reg
     }
@@ -157,7 +181,63 @@ impl StyleRegistry {
         let fill_id = self.intern_fill(style.fill.clone());
         let border_id = self.intern_border(style.border.clone());
         let fmt_id = self.intern_num_fmt(&style.number_format);
-        self.intern_xf(font_id, fill_id, border_id, fmt_id)
+        self.intern_xf(font_id, fill_id, border_id, fmt_id, 0)
+    }
+
+    /// Cell format using Excel's built-in "Hyperlink" cell style.
+    pub(crate) fn hyperlink_style(&mut self) -> usize {
+        if let Some(i) = self.hyperlink_xf {
+            return i;
+        }
+        let font = Font {
+            underline: true,
+            color: Some(Color::new("0563C1")),
+            ..Font::default()
+        };
+        let font_id = self.intern_font(font);
+        self.cell_style_xfs.push(font_id);
+        let xf_id = self.cell_style_xfs.len();
+        let i = self.intern_xf(font_id, 0, 0, 0, xf_id);
+        self.hyperlink_xf = Some(i);
+        i
+    }
+
+    /// Differential format used by conditional formatting rules.
+    pub(crate) fn register_dxf(&mut self, style: &Style) -> usize {
+        if let Some(&i) = self.dxf_index.get(style) {
+            return i;
+        }
+        // dxfs carry their own format code, but custom ids must still be unique
+        self.intern_num_fmt(&style.number_format);
+        let i = self.dxfs.len();
+        self.dxf_index.insert(style.clone(), i);
+        self.dxfs.push(style.clone());
+        i
+    }
+
+    fn dxf_xml(&self, style: &Style) -> String {
+        let mut xml = String::from("<dxf>");
+        if style.font != Font::default() {
+            xml.push_str(&style.font.to_dxf_xml());
+        }
+        if style.number_format != NumberFormat::General {
+            let id = match &style.number_format {
+                NumberFormat::Custom(code) => self.num_fmt_index.get(code).copied().unwrap_or(0),
+                fmt => fmt.builtin_id().unwrap_or(0),
+            };
+            xml.push_str(&format!(
+                "<numFmt numFmtId=\"{id}\" formatCode=\"{}\"/>",
+                xml_escape(style.number_format.format_code())
+            ));
+        }
+        if style.fill != Fill::None {
+            xml.push_str(&style.fill.to_dxf_xml());
+        }
+        if style.border != Border::default() {
+            xml.push_str(&style.border.to_xml());
+        }
+        xml.push_str("</dxf>");
+        xml
     }
 
     fn intern_font(&mut self, font: Font) -> usize {
@@ -213,8 +293,9 @@ impl StyleRegistry {
         fill_id: usize,
         border_id: usize,
         num_fmt_id: u32,
+        xf_id: usize,
     ) -> usize {
-        let key = (font_id, fill_id, border_id, num_fmt_id);
+        let key = (font_id, fill_id, border_id, num_fmt_id, xf_id);
         if let Some(&i) = self.xf_index.get(&key) {
             return i;
         }
@@ -237,7 +318,8 @@ impl StyleRegistry {
             out.push_str(&format!("<numFmts count=\"{}\">\n", self.num_fmts.len()));
             for (id, code) in &self.num_fmts {
                 out.push_str(&format!(
-                    "<numFmt numFmtId=\"{id}\" formatCode=\"{code}\"/>\n"
+                    "<numFmt numFmtId=\"{id}\" formatCode=\"{}\"/>\n",
+                    xml_escape(code)
                 ));
             }
             out.push_str("</numFmts>\n");
@@ -265,21 +347,79 @@ impl StyleRegistry {
         }
         out.push_str("</borders>\n");
 
-        out.push_str("<cellStyleXfs count=\"1\">\n");
+        out.push_str(&format!(
+            "<cellStyleXfs count=\"{}\">\n",
+            self.cell_style_xfs.len() + 1
+        ));
         out.push_str("<xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/>\n");
+        for font_id in &self.cell_style_xfs {
+            out.push_str(&format!(
+                "<xf numFmtId=\"0\" fontId=\"{font_id}\" fillId=\"0\" borderId=\"0\" applyNumberFormat=\"0\" applyFill=\"0\" applyBorder=\"0\" applyAlignment=\"0\" applyProtection=\"0\"/>\n"
+            ));
+        }
         out.push_str("</cellStyleXfs>\n");
 
         out.push_str(&format!("<cellXfs count=\"{}\">\n", self.xfs.len()));
-        for (font_id, fill_id, border_id, num_fmt_id) in &self.xfs {
-            out.push_str(&format!("<xf numFmtId=\"{num_fmt_id}\" fontId=\"{font_id}\" fillId=\"{fill_id}\" borderId=\"{border_id}\" xfId=\"0\"/>\n"));
+        for (font_id, fill_id, border_id, num_fmt_id, xf_id) in &self.xfs {
+            out.push_str(&format!("<xf numFmtId=\"{num_fmt_id}\" fontId=\"{font_id}\" fillId=\"{fill_id}\" borderId=\"{border_id}\" xfId=\"{xf_id}\"/>\n"));
         }
         out.push_str("</cellXfs>\n");
 
-        out.push_str("<cellStyles count=\"1\">\n");
+        out.push_str(&format!(
+            "<cellStyles count=\"{}\">\n",
+            self.cell_style_xfs.len() + 1
+        ));
+        if self.hyperlink_xf.is_some() {
+            out.push_str("<cellStyle name=\"Hyperlink\" xfId=\"1\" builtinId=\"8\"/>\n");
+        }
         out.push_str("<cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/>\n");
         out.push_str("</cellStyles>\n");
 
+        out.push_str(&format!("<dxfs count=\"{}\">\n", self.dxfs.len()));
+        for style in &self.dxfs {
+            out.push_str(&format!("{}\n", self.dxf_xml(style)));
+        }
+        out.push_str("</dxfs>\n");
+
         out.push_str("</styleSheet>");
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dxfs_are_deduplicated_in_order() {
+        let mut reg = StyleRegistry::new();
+        let bold = Style::new().bold();
+        let red = Style::new().bg("FF0000");
+        assert_eq!(reg.register_dxf(&bold), 0);
+        assert_eq!(reg.register_dxf(&red), 1);
+        assert_eq!(reg.register_dxf(&Style::new().bold()), 0);
+
+        let xml = reg.to_xml();
+        let dxfs = concat!(
+            "<dxfs count=\"2\">\n",
+            "<dxf><font><b/></font></dxf>\n",
+            "<dxf><fill><patternFill patternType=\"solid\"><fgColor rgb=\"FFFF0000\"/><bgColor rgb=\"FFFF0000\"/></patternFill></fill></dxf>\n",
+            "</dxfs>\n",
+        );
+        assert!(xml.contains(dxfs), "{xml}");
+    }
+
+    #[test]
+    fn dxf_number_formats_get_unique_ids() {
+        let mut reg = StyleRegistry::new();
+        reg.register(&Style::new().custom_format("0.0%"));
+        reg.register_dxf(&Style::new().custom_format("#,##0.000"));
+
+        let xml = reg.to_xml();
+        assert!(xml.contains("<numFmt numFmtId=\"164\" formatCode=\"0.0%\"/>"), "{xml}");
+        assert!(
+            xml.contains("<dxf><numFmt numFmtId=\"165\" formatCode=\"#,##0.000\"/></dxf>"),
+            "{xml}"
+        );
+    }
+}
@@ -7,6 +7,8 @@ pub enum NumberFormat {
     Currency, // e.g $1,234.56
     Date,     // e.g 2024-01-31
     DateTime, // e.g 2024-01-31 14:30
+    Time,     // e.g 14:30:05
+    Duration, // e.g 36:15:00
     Custom(String),
 }
 
@@ -20,7 +22,24 @@ impl NumberFormat {
             NumberFormat::Currency => Some(7),  // "$#,##0.00"
             NumberFormat::Date => Some(14),     // "m/d/yyyy"
             NumberFormat::DateTime => Some(22), // "m/d/yyyy h:mm"
+            NumberFormat::Time => Some(21),     // "h:mm:ss"
+            NumberFormat::Duration => Some(46), // "[h]:mm:ss"
             NumberFormat::Custom(_) => None,
         }
     }
+
+    pub(crate) fn format_code(&self) -> &str {
+        match self {
+            NumberFormat::General => "General",
+            NumberFormat::Integer => "0",
+            NumberFormat::Decimal2 => "#,##0.00",
+            NumberFormat::Percent => "0.00%",
+            NumberFormat::Currency => "\"$\"#,##0.00_);\\(\"$\"#,##0.00\\)",
+            NumberFormat::Date => "m/d/yyyy",
+            NumberFormat::DateTime => "m/d/yyyy h:mm",
+            NumberFormat::Time => "h:mm:ss",
+            NumberFormat::Duration => "[h]:mm:ss",
+            NumberFormat::Custom(code) => code,
+        }
+    }
 }
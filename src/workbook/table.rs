@@ -0,0 +1,467 @@
+use std::collections::HashSet;
+
+use crate::{
+    Result,
+    error::MrXlsxError,
+    workbook::{defined_name::is_valid_name, range::CellRange, xml_escape},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TotalFunction {
+    Sum,
+    Average,
+    Count,
+    CountNums,
+    Max,
+    Min,
+    StdDev,
+    Var,
+    /// Any formula, e.g. `SUM(Table1[Score])/2`.
+    Custom(String),
+}
+
+impl TotalFunction {
+    fn as_xml_attr(&self) -> &str {
+        match self {
+            TotalFunction::Sum => "sum",
+            TotalFunction::Average => "average",
+            TotalFunction::Count => "count",
+            TotalFunction::CountNums => "countNums",
+            TotalFunction::Max => "max",
+            TotalFunction::Min => "min",
+            TotalFunction::StdDev => "stdDev",
+            TotalFunction::Var => "var",
+            TotalFunction::Custom(_) => "custom",
+        }
+    }
+
+    fn subtotal_code(&self) -> Option<u32> {
+        match self {
+            TotalFunction::Average => Some(101),
+            TotalFunction::CountNums => Some(102),
+            TotalFunction::Count => Some(103),
+            TotalFunction::Max => Some(104),
+            TotalFunction::Min => Some(105),
+            TotalFunction::StdDev => Some(107),
+            TotalFunction::Sum => Some(109),
+            TotalFunction::Var => Some(110),
+            TotalFunction::Custom(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    /// Header text, must match the header cell.
+    pub name: String,
+    pub total_function: Option<TotalFunction>,
+    pub total_label: Option<String>,
+    /// Calculated column formula, e.g. `[@Price]*[@Qty]`.
+    pub formula: Option<String>,
+}
+
+impl TableColumn {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        TableColumn {
+            name: name.into(),
+            total_function: None,
+            total_label: None,
+            formula: None,
+        }
+    }
+
+    pub fn total_function(mut self, function: TotalFunction) -> Self {
+        self.total_function = Some(function);
+        self
+    }
+    pub fn total_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.total_label = Some(label.into());
+        self
+    }
+    pub fn formula<S: Into<String>>(mut self, formula: S) -> Self {
+        self.formula = Some(formula.into());
+        self
+    }
+}
+
+/// Excel table (ListObject) over a block of cells.
+///
+/// Header, totals and calculated column cells that have not been written when the
+/// table is added are filled in from the column definitions; cells written later
+/// with a non-blank value take precedence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// Workbook-unique name, defaults to `Table1`, `Table2`, ...
+    pub name: Option<String>,
+    /// Built-in table style, e.g. `TableStyleMedium2`. `None` for no style.
+    pub style: Option<String>,
+    pub header_row: bool,
+    pub totals_row: bool,
+    pub autofilter: bool,
+    pub banded_rows: bool,
+    pub banded_columns: bool,
+    pub first_column: bool,
+    pub last_column: bool,
+    pub columns: Vec<TableColumn>,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Table {
+            name: None,
+            style: Some("TableStyleMedium2".into()),
+            header_row: true,
+            totals_row: false,
+            autofilter: true,
+            banded_rows: true,
+            banded_columns: false,
+            first_column: false,
+            last_column: false,
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    pub fn style<S: Into<String>>(mut self, style: S) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+    pub fn no_style(mut self) -> Self {
+        self.style = None;
+        self
+    }
+    pub fn no_header_row(mut self) -> Self {
+        self.header_row = false;
+        self
+    }
+    pub fn totals_row(mut self) -> Self {
+        self.totals_row = true;
+        self
+    }
+    pub fn no_autofilter(mut self) -> Self {
+        self.autofilter = false;
+        self
+    }
+    pub fn banded_rows(mut self, enabled: bool) -> Self {
+        self.banded_rows = enabled;
+        self
+    }
+    pub fn banded_columns(mut self, enabled: bool) -> Self {
+        self.banded_columns = enabled;
+        self
+    }
+    pub fn first_column(mut self, enabled: bool) -> Self {
+        self.first_column = enabled;
+        self
+    }
+    pub fn last_column(mut self, enabled: bool) -> Self {
+        self.last_column = enabled;
+        self
+    }
+    pub fn columns(mut self, columns: Vec<TableColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+}
+
+/// Table names in use across the workbook, compared case-insensitively.
+#[derive(Default)]
+pub(crate) struct TableRegistry {
+    names: HashSet<String>,
+}
+
+impl TableRegistry {
+    pub(crate) fn reserve(&mut self, name: Option<&str>) -> Result<String> {
+        let name = match name {
+            Some(name) => {
+                validate_table_name(name)?;
+                name.to_string()
+            }
+            None => (self.names.len() + 1..)
+                .map(|n| format!("Table{n}"))
+                .find(|n| !self.names.contains(&n.to_lowercase()))
+                .unwrap(),
+        };
+        if !self.names.insert(name.to_lowercase()) {
+            return Err(MrXlsxError::AlreadyExists(format!(
+                "Table '{name}' already exists"
+            )));
+        }
+        Ok(name)
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name.to_lowercase())
+    }
+
+    pub(crate) fn release(&mut self, name: &str) {
+        self.names.remove(&name.to_lowercase());
+    }
+}
+
+fn validate_table_name(name: &str) -> Result<()> {
+    if is_valid_name(name) {
+        Ok(())
+    } else {
+        Err(MrXlsxError::InvalidArgument(format!(
+            "'{name}' is not a valid table name"
+        )))
+    }
+}
+
+/// A table placed on a sheet, with its name and columns resolved.
+pub(crate) struct SheetTable {
+    pub(crate) name: String,
+    pub(crate) range: CellRange,
+    pub(crate) table: Table,
+}
+
+impl SheetTable {
+    pub(crate) fn new(name: String, range: CellRange, mut table: Table) -> Result<Self> {
+        let width = (range.last_col - range.first_col + 1) as usize;
+        if table.columns.len() > width {
+            return Err(MrXlsxError::InvalidArgument(format!(
+                "table '{name}' has {} columns but {} spans {width}",
+                table.columns.len(),
+                range.to_ref()
+            )));
+        }
+        for i in table.columns.len()..width {
+            table
+                .columns
+                .push(TableColumn::new(format!("Column{}", i + 1)));
+        }
+
+        let mut seen = HashSet::new();
+        for column in &table.columns {
+            if !seen.insert(column.name.to_lowercase()) {
+                return Err(MrXlsxError::InvalidArgument(format!(
+                    "table '{name}' has duplicate column '{}'",
+                    column.name
+                )));
+            }
+        }
+
+        let rows = range.last_row - range.first_row + 1;
+        let min_rows = 1 + table.header_row as u32 + table.totals_row as u32;
+        if rows < min_rows {
+            return Err(MrXlsxError::InvalidRange(format!(
+                "table '{name}' needs at least {min_rows} rows, {} has {rows}",
+                range.to_ref()
+            )));
+        }
+
+        Ok(SheetTable { name, range, table })
+    }
+
+    /// Rows holding data, excluding header and totals rows.
+    pub(crate) fn data_rows(&self) -> std::ops::RangeInclusive<u32> {
+        let first = self.range.first_row + self.table.header_row as u32;
+        let last = self.range.last_row - self.table.totals_row as u32;
+        first..=last
+    }
+
+    /// Formula written to the totals row cell of a column.
+    pub(crate) fn total_formula(&self, column: &TableColumn) -> Option<String> {
+        match column.total_function.as_ref()? {
+            TotalFunction::Custom(f) => Some(f.trim_start_matches('=').to_string()),
+            function => Some(format!(
+                "SUBTOTAL({},{}[{}])",
+                function.subtotal_code().unwrap(),
+                self.name,
+                escape_column_name(&column.name)
+            )),
+        }
+    }
+
+    /// Calculated column formula in file form, with `[@Col]` expanded to
+    /// `Table1[[#This Row],[Col]]`.
+    pub(crate) fn calculated_formula(&self, column: &TableColumn) -> Option<String> {
+        let formula = column.formula.as_ref()?.trim_start_matches('=');
+        Some(expand_this_row(formula, &self.name))
+    }
+
+    pub(crate) fn to_xml(&self, id: usize) -> String {
+        let name = xml_escape(&self.name);
+        let mut xml = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<table xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+                r#"id="{}" name="{}" displayName="{}" ref="{}""#,
+            ),
+            id,
+            name,
+            name,
+            self.range.to_ref()
+        );
+        if !self.table.header_row {
+            xml.push_str(r#" headerRowCount="0""#);
+        }
+        if self.table.totals_row {
+            xml.push_str(r#" totalsRowCount="1""#);
+        } else {
+            xml.push_str(r#" totalsRowShown="0""#);
+        }
+        xml.push('>');
+
+        if self.table.header_row && self.table.autofilter {
+            let filter_range = CellRange {
+                last_row: self.range.last_row - self.table.totals_row as u32,
+                ..self.range
+            };
+            xml.push_str(&format!(r#"<autoFilter ref="{}"/>"#, filter_range.to_ref()));
+        }
+
+        xml.push_str(&format!(
+            r#"<tableColumns count="{}">"#,
+            self.table.columns.len()
+        ));
+        for (i, column) in self.table.columns.iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<tableColumn id="{}" name="{}""#,
+                i + 1,
+                xml_escape(&column.name)
+            ));
+            if self.table.totals_row {
+                if let Some(label) = &column.total_label {
+                    xml.push_str(&format!(r#" totalsRowLabel="{}""#, xml_escape(label)));
+                }
+                if let Some(function) = &column.total_function {
+                    xml.push_str(&format!(
+                        r#" totalsRowFunction="{}""#,
+                        function.as_xml_attr()
+                    ));
+                }
+            }
+
+            let calculated = self.calculated_formula(column);
+            let custom_total = match &column.total_function {
+                Some(TotalFunction::Custom(_)) if self.table.totals_row => {
+                    self.total_formula(column)
+                }
+                _ => None,
+            };
+            if calculated.is_none() && custom_total.is_none() {
+                xml.push_str("/>");
+                continue;
+            }
+            xml.push('>');
+            if let Some(f) = calculated {
+                xml.push_str(&format!(
+                    "<calculatedColumnFormula>{}</calculatedColumnFormula>",
+                    xml_escape(&f)
+                ));
+            }
+            if let Some(f) = custom_total {
+                xml.push_str(&format!(
+                    "<totalsRowFormula>{}</totalsRowFormula>",
+                    xml_escape(&f)
+                ));
+            }
+            xml.push_str("</tableColumn>");
+        }
+        xml.push_str("</tableColumns>");
+
+        if let Some(style) = &self.table.style {
+            xml.push_str(&format!(
+                r#"<tableStyleInfo name="{}" showFirstColumn="{}" showLastColumn="{}" showRowStripes="{}" showColumnStripes="{}"/>"#,
+                xml_escape(style),
+                self.table.first_column as u8,
+                self.table.last_column as u8,
+                self.table.banded_rows as u8,
+                self.table.banded_columns as u8
+            ));
+        }
+        xml.push_str("</table>");
+        xml
+    }
+}
+
+// special characters in structured reference column names are escaped with '
+fn escape_column_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for ch in name.chars() {
+        if matches!(ch, '[' | ']' | '#' | '\'') {
+            out.push('\'');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn expand_this_row(formula: &str, table: &str) -> String {
+    let mut out = String::with_capacity(formula.len());
+    let mut rest = formula;
+    while let Some(start) = rest.find("[@") {
+        let after = &rest[start + 2..];
+        // [@[Col Name]] or [@Col]
+        let (column, consumed) = match after.strip_prefix('[') {
+            Some(inner) => match find_closing_bracket(inner) {
+                Some(end) if inner[end + 1..].starts_with(']') => (&inner[..end], end + 3),
+                _ => break,
+            },
+            None => match find_closing_bracket(after) {
+                Some(end) => (&after[..end], end + 1),
+                None => break,
+            },
+        };
+        // a malformed `[@` breaks out above and is copied as written
+        out.push_str(&rest[..start]);
+        out.push_str(&format!("{table}[[#This Row],[{column}]]"));
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// position of the first `]` not escaped with '
+fn find_closing_bracket(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\'' => escaped = true,
+            ']' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_this_row_references() {
+        assert_eq!(
+            expand_this_row("[@Price]*[@[Unit Count]]", "Sales"),
+            "Sales[[#This Row],[Price]]*Sales[[#This Row],[Unit Count]]"
+        );
+        assert_eq!(expand_this_row("SUM(A1:A3)", "Sales"), "SUM(A1:A3)");
+    }
+
+    #[test]
+    fn keeps_escaped_brackets_in_column_names() {
+        assert_eq!(
+            expand_this_row("[@[Size '[cm']]]", "T"),
+            "T[[#This Row],[Size '[cm']]]"
+        );
+    }
+
+    #[test]
+    fn leaves_malformed_references_unchanged() {
+        assert_eq!(expand_this_row("A[@B", "T"), "A[@B");
+        assert_eq!(expand_this_row("A[@[B]", "T"), "A[@[B]");
+        assert_eq!(expand_this_row("[@X]+A[@B", "T"), "T[[#This Row],[X]]+A[@B");
+    }
+}
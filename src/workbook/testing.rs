@@ -0,0 +1,169 @@
+//! Helpers for the round-trip tests: finish a workbook in a temporary directory and
+//! read its parts back, parsed where element order matters.
+
+use std::{collections::BTreeMap, io::Read};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::{
+    csv::reader::XlsxReader,
+    workbook::{Workbook, builder::WorkbookBuilder},
+};
+
+/// A finished workbook file, removed with its directory when dropped.
+pub(crate) struct Written {
+    _dir: tempfile::TempDir,
+    pub(crate) path: String,
+}
+
+impl Written {
+    /// Content of a package part, checking it is declared in `[Content_Types].xml`.
+    pub(crate) fn part(&self, part: &str) -> String {
+        let content_types = String::from_utf8(self.bytes("[Content_Types].xml")).unwrap();
+        let extension = part.rsplit('.').next().unwrap();
+        assert!(
+            content_types.contains(&format!("PartName=\"/{part}\""))
+                || content_types.contains(&format!("Extension=\"{extension}\"")),
+            "{part} has no content type: {content_types}"
+        );
+        String::from_utf8(self.bytes(part)).unwrap()
+    }
+
+    pub(crate) fn bytes(&self, part: &str) -> Vec<u8> {
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&self.path).unwrap()).unwrap();
+        let mut bytes = vec![];
+        zip.by_name(part).unwrap().read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub(crate) fn has_part(&self, part: &str) -> bool {
+        let zip = zip::ZipArchive::new(std::fs::File::open(&self.path).unwrap()).unwrap();
+        zip.index_for_name(part).is_some()
+    }
+
+    /// Rows of `sheet` as read back by the crate's own reader.
+    pub(crate) fn rows(&self, sheet: &str) -> Vec<Vec<String>> {
+        XlsxReader::open(&self.path)
+            .unwrap()
+            .stream_rows(sheet)
+            .unwrap()
+            .map(|row| row.unwrap().cells)
+            .collect()
+    }
+}
+
+/// A workbook finished into `bytes`, e.g. by `Workbook::finish_stream`.
+pub(crate) fn from_bytes(bytes: &[u8]) -> Written {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.xlsx").to_str().unwrap().to_string();
+    std::fs::write(&path, bytes).unwrap();
+    Written { _dir: dir, path }
+}
+
+/// Finish a workbook from a `configure`d builder once `build` has filled it.
+pub(crate) fn write_with(
+    configure: impl FnOnce(WorkbookBuilder<String>) -> WorkbookBuilder<String>,
+    build: impl FnOnce(&mut Workbook),
+) -> Written {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.xlsx").to_str().unwrap().to_string();
+    let mut wb = configure(WorkbookBuilder::new(path.clone()))
+        .build()
+        .unwrap();
+    build(&mut wb);
+    wb.finish().unwrap();
+    Written { _dir: dir, path }
+}
+
+pub(crate) fn write(build: impl FnOnce(&mut Workbook)) -> Written {
+    write_with(|builder| builder, build)
+}
+
+/// One part of a default workbook filled by `build`.
+pub(crate) fn written_part(build: impl FnOnce(&mut Workbook), part: &str) -> String {
+    write(build).part(part)
+}
+
+/// An element of a parsed part, with its qualified name and attributes.
+#[derive(Debug)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attrs: BTreeMap<String, String>,
+    depth: usize,
+}
+
+/// Every element of `xml` in document order.
+pub(crate) fn elements(xml: &str) -> Vec<Element> {
+    let mut reader = Reader::from_str(xml);
+    let mut elements = vec![];
+    let mut depth = 0;
+    loop {
+        let (e, empty) = match reader.read_event().unwrap() {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(_) => {
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => return elements,
+            _ => continue,
+        };
+        let attrs = e
+            .attributes()
+            .map(|attr| {
+                let attr = attr.unwrap();
+                let key = String::from_utf8(attr.key.as_ref().to_vec()).unwrap();
+                let value = attr.decode_and_unescape_value(reader.decoder()).unwrap();
+                (key, value.into_owned())
+            })
+            .collect();
+        elements.push(Element {
+            name: String::from_utf8(e.name().as_ref().to_vec()).unwrap(),
+            attrs,
+            depth,
+        });
+        if !empty {
+            depth += 1;
+        }
+    }
+}
+
+/// Attributes of each `name` element, in document order.
+pub(crate) fn attrs(xml: &str, name: &str) -> Vec<BTreeMap<String, String>> {
+    elements(xml)
+        .into_iter()
+        .filter(|e| e.name == name)
+        .map(|e| e.attrs)
+        .collect()
+}
+
+/// Names of the children of the first `parent` element, in order.
+pub(crate) fn children(xml: &str, parent: &str) -> Vec<String> {
+    let elements = elements(xml);
+    let Some(at) = elements.iter().position(|e| e.name == parent) else {
+        return vec![];
+    };
+    let depth = elements[at].depth + 1;
+    elements[at + 1..]
+        .iter()
+        .take_while(|e| e.depth >= depth)
+        .filter(|e| e.depth == depth)
+        .map(|e| e.name.clone())
+        .collect()
+}
+
+/// Target of each relationship in a `.rels` part.
+pub(crate) fn targets(xml: &str) -> Vec<String> {
+    attrs(xml, "Relationship")
+        .into_iter()
+        .map(|mut rel| rel.remove("Target").unwrap())
+        .collect()
+}
+
+/// Attribute map to compare with an entry of [`attrs`].
+pub(crate) fn attr_map<const N: usize>(pairs: [(&str, &str); N]) -> BTreeMap<String, String> {
+    pairs
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
@@ -0,0 +1,285 @@
+use crate::{Result, error::MrXlsxError, workbook::xml_escape};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationCriteria {
+    Between(String, String),
+    NotBetween(String, String),
+    Equal(String),
+    NotEqual(String),
+    GreaterThan(String),
+    LessThan(String),
+    GreaterThanOrEqual(String),
+    LessThanOrEqual(String),
+}
+
+impl ValidationCriteria {
+    /// Operands are numbers or formulas, e.g. `10`, `DATE(2024,1,1)` or `$B$1`.
+    pub fn between<T: ToString>(min: T, max: T) -> Self {
+        ValidationCriteria::Between(min.to_string(), max.to_string())
+    }
+    pub fn not_between<T: ToString>(min: T, max: T) -> Self {
+        ValidationCriteria::NotBetween(min.to_string(), max.to_string())
+    }
+    pub fn equal<T: ToString>(v: T) -> Self {
+        ValidationCriteria::Equal(v.to_string())
+    }
+    pub fn not_equal<T: ToString>(v: T) -> Self {
+        ValidationCriteria::NotEqual(v.to_string())
+    }
+    pub fn greater_than<T: ToString>(v: T) -> Self {
+        ValidationCriteria::GreaterThan(v.to_string())
+    }
+    pub fn less_than<T: ToString>(v: T) -> Self {
+        ValidationCriteria::LessThan(v.to_string())
+    }
+    pub fn greater_than_or_equal<T: ToString>(v: T) -> Self {
+        ValidationCriteria::GreaterThanOrEqual(v.to_string())
+    }
+    pub fn less_than_or_equal<T: ToString>(v: T) -> Self {
+        ValidationCriteria::LessThanOrEqual(v.to_string())
+    }
+
+    pub(crate) fn operator(&self) -> &str {
+        match self {
+            ValidationCriteria::Between(..) => "between",
+            ValidationCriteria::NotBetween(..) => "notBetween",
+            ValidationCriteria::Equal(_) => "equal",
+            ValidationCriteria::NotEqual(_) => "notEqual",
+            ValidationCriteria::GreaterThan(_) => "greaterThan",
+            ValidationCriteria::LessThan(_) => "lessThan",
+            ValidationCriteria::GreaterThanOrEqual(_) => "greaterThanOrEqual",
+            ValidationCriteria::LessThanOrEqual(_) => "lessThanOrEqual",
+        }
+    }
+
+    pub(crate) fn formulas(&self) -> (&str, Option<&str>) {
+        match self {
+            ValidationCriteria::Between(a, b) | ValidationCriteria::NotBetween(a, b) => {
+                (a, Some(b))
+            }
+            ValidationCriteria::Equal(a)
+            | ValidationCriteria::NotEqual(a)
+            | ValidationCriteria::GreaterThan(a)
+            | ValidationCriteria::LessThan(a)
+            | ValidationCriteria::GreaterThanOrEqual(a)
+            | ValidationCriteria::LessThanOrEqual(a) => (a, None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationRule {
+    /// No constraint, only the input prompt is shown.
+    Any,
+    WholeNumber(ValidationCriteria),
+    Decimal(ValidationCriteria),
+    Date(ValidationCriteria),
+    Time(ValidationCriteria),
+    TextLength(ValidationCriteria),
+    /// Dropdown of inline values, at most 255 characters in total.
+    List(Vec<String>),
+    /// Dropdown sourced from a range, e.g. `'Lists'!$A$1:$A$10`.
+    ListRange(String),
+    /// Formula that must evaluate to TRUE, e.g. `ISNUMBER(A1)`.
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationErrorStyle {
+    Stop,
+    Warning,
+    Information,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataValidation {
+    pub rule: ValidationRule,
+    pub allow_blank: bool,
+    pub show_dropdown: bool,
+    pub input_title: Option<String>,
+    pub input_message: Option<String>,
+    pub error_title: Option<String>,
+    pub error_message: Option<String>,
+    pub error_style: ValidationErrorStyle,
+}
+
+impl DataValidation {
+    pub fn new(rule: ValidationRule) -> Self {
+        DataValidation {
+            rule,
+            allow_blank: true,
+            show_dropdown: true,
+            input_title: None,
+            input_message: None,
+            error_title: None,
+            error_message: None,
+            error_style: ValidationErrorStyle::Stop,
+        }
+    }
+
+    pub fn list<S: Into<String>>(values: Vec<S>) -> Self {
+        DataValidation::new(ValidationRule::List(
+            values.into_iter().map(Into::into).collect(),
+        ))
+    }
+    pub fn list_range<S: Into<String>>(range: S) -> Self {
+        DataValidation::new(ValidationRule::ListRange(range.into()))
+    }
+    pub fn whole_number(criteria: ValidationCriteria) -> Self {
+        DataValidation::new(ValidationRule::WholeNumber(criteria))
+    }
+    pub fn decimal(criteria: ValidationCriteria) -> Self {
+        DataValidation::new(ValidationRule::Decimal(criteria))
+    }
+    pub fn date(criteria: ValidationCriteria) -> Self {
+        DataValidation::new(ValidationRule::Date(criteria))
+    }
+    pub fn time(criteria: ValidationCriteria) -> Self {
+        DataValidation::new(ValidationRule::Time(criteria))
+    }
+    pub fn text_length(criteria: ValidationCriteria) -> Self {
+        DataValidation::new(ValidationRule::TextLength(criteria))
+    }
+    pub fn custom<S: Into<String>>(formula: S) -> Self {
+        DataValidation::new(ValidationRule::Custom(formula.into()))
+    }
+
+    pub fn allow_blank(mut self, allow: bool) -> Self {
+        self.allow_blank = allow;
+        self
+    }
+    /// Hide the in-cell dropdown arrow of list validations.
+    pub fn hide_dropdown(mut self) -> Self {
+        self.show_dropdown = false;
+        self
+    }
+    pub fn input_message<S: Into<String>>(mut self, title: S, message: S) -> Self {
+        self.input_title = Some(title.into());
+        self.input_message = Some(message.into());
+        self
+    }
+    pub fn error_message<S: Into<String>>(mut self, title: S, message: S) -> Self {
+        self.error_title = Some(title.into());
+        self.error_message = Some(message.into());
+        self
+    }
+    pub fn error_style(mut self, style: ValidationErrorStyle) -> Self {
+        self.error_style = style;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if let ValidationRule::List(values) = &self.rule {
+            let len = values.iter().map(|v| v.chars().count() + 1).sum::<usize>();
+            if len > 256 {
+                return Err(MrXlsxError::InvalidArgument(
+                    "list validation values exceed 255 characters, use a list range".into(),
+                ));
+            }
+            if values.iter().any(|v| v.contains(',')) {
+                return Err(MrXlsxError::InvalidArgument(
+                    "list validation values cannot contain commas, use a list range".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_xml(&self, sqref: &str) -> String {
+        let (kind, criteria) = match &self.rule {
+            ValidationRule::Any => (None, None),
+            ValidationRule::WholeNumber(c) => (Some("whole"), Some(c)),
+            ValidationRule::Decimal(c) => (Some("decimal"), Some(c)),
+            ValidationRule::Date(c) => (Some("date"), Some(c)),
+            ValidationRule::Time(c) => (Some("time"), Some(c)),
+            ValidationRule::TextLength(c) => (Some("textLength"), Some(c)),
+            ValidationRule::List(_) | ValidationRule::ListRange(_) => (Some("list"), None),
+            ValidationRule::Custom(_) => (Some("custom"), None),
+        };
+
+        let mut xml = String::from("<dataValidation");
+        if let Some(kind) = kind {
+            xml.push_str(&format!(" type=\"{kind}\""));
+        }
+        match self.error_style {
+            ValidationErrorStyle::Stop => {}
+            ValidationErrorStyle::Warning => xml.push_str(" errorStyle=\"warning\""),
+            ValidationErrorStyle::Information => xml.push_str(" errorStyle=\"information\""),
+        }
+        if let Some(c) = criteria {
+            xml.push_str(&format!(" operator=\"{}\"", c.operator()));
+        }
+        if self.allow_blank {
+            xml.push_str(" allowBlank=\"1\"");
+        }
+        // the attribute is inverted: showDropDown="1" hides the arrow
+        if !self.show_dropdown {
+            xml.push_str(" showDropDown=\"1\"");
+        }
+        xml.push_str(" showInputMessage=\"1\" showErrorMessage=\"1\"");
+        for (attr, value) in [
+            ("errorTitle", &self.error_title),
+            ("error", &self.error_message),
+            ("promptTitle", &self.input_title),
+            ("prompt", &self.input_message),
+        ] {
+            if let Some(v) = value {
+                xml.push_str(&format!(" {attr}=\"{}\"", xml_escape(v)));
+            }
+        }
+        xml.push_str(&format!(" sqref=\"{sqref}\">"));
+
+        let (formula1, formula2) = match &self.rule {
+            ValidationRule::Any => (None, None),
+            ValidationRule::List(values) => {
+                // a string literal in the formula, so embedded quotes are doubled
+                let list = values.join(",").replace('"', "\"\"");
+                (Some(format!("\"{list}\"")), None)
+            }
+            ValidationRule::ListRange(f) | ValidationRule::Custom(f) => {
+                (Some(f.trim_start_matches('=').to_string()), None)
+            }
+            _ => {
+                let (a, b) = criteria.map(|c| c.formulas()).unwrap();
+                (Some(a.to_string()), b.map(|b| b.to_string()))
+            }
+        };
+        if let Some(f) = formula1 {
+            xml.push_str(&format!("<formula1>{}</formula1>", xml_escape(&f)));
+        }
+        if let Some(f) = formula2 {
+            xml.push_str(&format!("<formula2>{}</formula2>", xml_escape(&f)));
+        }
+        xml.push_str("</dataValidation>");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_quotes_are_doubled() {
+        let xml = DataValidation::list(vec!["Yes", "say \"no\""]).to_xml("A1");
+        assert!(
+            xml.contains("<formula1>&quot;Yes,say &quot;&quot;no&quot;&quot;&quot;</formula1>"),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn list_rejects_commas_and_long_lists() {
+        assert!(matches!(
+            DataValidation::list(vec!["a,b"]).validate(),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+        let fits = vec!["x".repeat(127), "y".repeat(127)];
+        assert!(DataValidation::list(fits).validate().is_ok());
+        let too_long = vec!["x".repeat(128), "y".repeat(127)];
+        assert!(matches!(
+            DataValidation::list(too_long).validate(),
+            Err(MrXlsxError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::workbook::make_cell_ref;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Pane {
+    /// Rows above and columns left of the split stay in place while scrolling.
+    Frozen { rows: u32, cols: u32 },
+    /// Independently scrollable panes, positions in twentieths of a point.
+    Split { x: f64, y: f64 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SheetView {
+    pub(crate) pane: Option<Pane>,
+    pub(crate) zoom: Option<u16>,
+    pub(crate) hide_gridlines: bool,
+    pub(crate) hide_headings: bool,
+    pub(crate) right_to_left: bool,
+    /// 0-based (row, col) of the active cell.
+    pub(crate) selection: Option<(u32, u32)>,
+}
+
+impl SheetView {
+    pub(crate) fn to_xml(&self, tab_selected: bool) -> String {
+        let mut xml = String::from("<sheetViews><sheetView");
+        if tab_selected {
+            xml.push_str(" tabSelected=\"1\"");
+        }
+        if self.hide_gridlines {
+            xml.push_str(" showGridLines=\"0\"");
+        }
+        if self.hide_headings {
+            xml.push_str(" showRowColHeaders=\"0\"");
+        }
+        if self.right_to_left {
+            xml.push_str(" rightToLeft=\"1\"");
+        }
+        if let Some(zoom) = self.zoom {
+            xml.push_str(&format!(" zoomScale=\"{zoom}\" zoomScaleNormal=\"{zoom}\""));
+        }
+        xml.push_str(" workbookViewId=\"0\"");
+
+        let active_pane = self.active_pane();
+        if self.pane.is_none() && self.selection.is_none() {
+            xml.push_str("/></sheetViews>");
+            return xml;
+        }
+        xml.push('>');
+
+        match &self.pane {
+            Some(Pane::Frozen { rows, cols }) => {
+                let top_left = make_cell_ref(rows + 1, *cols);
+                xml.push_str("<pane");
+                if *cols > 0 {
+                    xml.push_str(&format!(" xSplit=\"{cols}\""));
+                }
+                if *rows > 0 {
+                    xml.push_str(&format!(" ySplit=\"{rows}\""));
+                }
+                xml.push_str(&format!(
+                    " topLeftCell=\"{top_left}\" activePane=\"{}\" state=\"frozen\"/>",
+                    active_pane.unwrap_or("bottomRight")
+                ));
+            }
+            Some(Pane::Split { x, y }) => {
+                xml.push_str("<pane");
+                if *x > 0.0 {
+                    xml.push_str(&format!(" xSplit=\"{x}\""));
+                }
+                if *y > 0.0 {
+                    xml.push_str(&format!(" ySplit=\"{y}\""));
+                }
+                xml.push_str(&format!(
+                    " activePane=\"{}\"/>",
+                    active_pane.unwrap_or("bottomRight")
+                ));
+            }
+            None => {}
+        }
+
+        let active_cell = match (self.selection, &self.pane) {
+            (Some((row, col)), _) => Some(make_cell_ref(row + 1, col)),
+            (None, Some(Pane::Frozen { rows, cols })) => Some(make_cell_ref(rows + 1, *cols)),
+            (None, _) => None,
+        };
+        if let Some(cell) = active_cell {
+            xml.push_str("<selection");
+            if let Some(pane) = active_pane {
+                xml.push_str(&format!(" pane=\"{pane}\""));
+            }
+            xml.push_str(&format!(" activeCell=\"{cell}\" sqref=\"{cell}\"/>"));
+        }
+
+        xml.push_str("</sheetView></sheetViews>");
+        xml
+    }
+
+    fn active_pane(&self) -> Option<&'static str> {
+        let (has_x, has_y) = match &self.pane {
+            Some(Pane::Frozen { rows, cols }) => (*cols > 0, *rows > 0),
+            Some(Pane::Split { x, y }) => (*x > 0.0, *y > 0.0),
+            None => return None,
+        };
+        match (has_x, has_y) {
+            (true, true) => Some("bottomRight"),
+            (false, true) => Some("bottomLeft"),
+            (true, false) => Some("topRight"),
+            (false, false) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_view() {
+        assert_eq!(
+            SheetView::default().to_xml(true),
+            r#"<sheetViews><sheetView tabSelected="1" workbookViewId="0"/></sheetViews>"#
+        );
+    }
+
+    #[test]
+    fn frozen_panes() {
+        let view = SheetView {
+            pane: Some(Pane::Frozen { rows: 1, cols: 0 }),
+            zoom: Some(150),
+            ..SheetView::default()
+        };
+        assert_eq!(
+            view.to_xml(false),
+            concat!(
+                r#"<sheetViews><sheetView zoomScale="150" zoomScaleNormal="150" workbookViewId="0">"#,
+                r#"<pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/>"#,
+                r#"<selection pane="bottomLeft" activeCell="A2" sqref="A2"/></sheetView></sheetViews>"#
+            )
+        );
+
+        let view = SheetView {
+            pane: Some(Pane::Frozen { rows: 2, cols: 1 }),
+            selection: Some((4, 3)),
+            ..SheetView::default()
+        };
+        assert_eq!(
+            view.to_xml(true),
+            concat!(
+                r#"<sheetViews><sheetView tabSelected="1" workbookViewId="0">"#,
+                r#"<pane xSplit="1" ySplit="2" topLeftCell="B3" activePane="bottomRight" state="frozen"/>"#,
+                r#"<selection pane="bottomRight" activeCell="D5" sqref="D5"/></sheetView></sheetViews>"#
+            )
+        );
+    }
+
+    #[test]
+    fn split_panes() {
+        let view = SheetView {
+            pane: Some(Pane::Split { x: 1500.5, y: 0.0 }),
+            hide_gridlines: true,
+            ..SheetView::default()
+        };
+        assert_eq!(
+            view.to_xml(false),
+            concat!(
+                r#"<sheetViews><sheetView showGridLines="0" workbookViewId="0">"#,
+                r#"<pane xSplit="1500.5" activePane="topRight"/></sheetView></sheetViews>"#
+            )
+        );
+
+        let view = SheetView {
+            pane: Some(Pane::Split { x: 0.0, y: 600.0 }),
+            selection: Some((9, 0)),
+            ..SheetView::default()
+        };
+        assert_eq!(
+            view.to_xml(false),
+            concat!(
+                r#"<sheetViews><sheetView workbookViewId="0"><pane ySplit="600" activePane="bottomLeft"/>"#,
+                r#"<selection pane="bottomLeft" activeCell="A10" sqref="A10"/></sheetView></sheetViews>"#
+            )
+        );
+    }
+
+    #[test]
+    fn selection_without_pane() {
+        let view = SheetView {
+            hide_headings: true,
+            right_to_left: true,
+            selection: Some((1, 2)),
+            ..SheetView::default()
+        };
+        assert_eq!(
+            view.to_xml(false),
+            concat!(
+                r#"<sheetViews><sheetView showRowColHeaders="0" rightToLeft="1" workbookViewId="0">"#,
+                r#"<selection activeCell="C2" sqref="C2"/></sheetView></sheetViews>"#
+            )
+        );
+    }
+}